@@ -1,8 +1,15 @@
-use std::{error::Error, str::from_utf8, vec};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    vec,
+};
 
 use lazy_static::lazy_static;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use regex::Regex;
 
+pub mod words;
+
 lazy_static! {
     pub static ref NUMBER_REGEX: Regex = Regex::new(r"(\d)").unwrap();
     pub static ref STRING_NUMBER_REGEX: Regex =
@@ -20,7 +27,7 @@ fn decode_string_number(input: &str) -> &str {
         "seven" => "7",
         "eight" => "8",
         "nine" => "9",
-        _ => "0",
+        _ => input,
     }
 }
 
@@ -49,38 +56,226 @@ pub fn numbers_in_line(line: &str, re: &Regex) -> Result<Vec<usize>, Box<dyn Err
     let mut all_numbers = vec![];
 
     for (full_match, [_]) in re.captures_iter(line).map(|x| x.extract()) {
-        all_numbers.push(full_match.parse::<usize>()?);
+        all_numbers.push(decode_string_number(full_match).parse::<usize>()?);
     }
 
-    if all_numbers.is_empty() == true {
+    if all_numbers.is_empty() {
         return Err("no numbers found".into());
     }
 
     Ok(all_numbers)
 }
 
-pub fn extract_calibration_number(all_numbers: Vec<usize>) -> Option<usize> {
-    let mut res: Vec<usize> = Vec::with_capacity(2);
-    match all_numbers.len() {
-        1 => {
-            res.push(*all_numbers.first().unwrap());
-            res.push(*all_numbers.first().unwrap());
+/// How the selected digits of a line are combined into a single calibration value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combine {
+    /// Write the digits one after another in the rule's base, e.g. [1, 3] -> 13
+    Concat,
+    /// Add all selected digits, e.g. [1, 3] -> 4
+    Sum,
+    /// Multiply all selected digits, e.g. [2, 3] -> 6
+    Product,
+}
+
+/// Describes how a calibration value is derived from the digits found in a line.
+///
+/// The default rule is the one from the puzzle: first and last digit, base 10, concatenated.
+/// The fields are private, so every rule goes through `new` and has a base within 2..=36.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalibrationRule {
+    first: usize,
+    last: usize,
+    base: u32,
+    combine: Combine,
+}
+
+impl Default for CalibrationRule {
+    fn default() -> Self {
+        CalibrationRule {
+            first: 1,
+            last: 1,
+            base: 10,
+            combine: Combine::Concat,
         }
-        _ => {
-            res.push(*all_numbers.first().unwrap());
-            res.push(*all_numbers.last().unwrap());
+    }
+}
+
+impl CalibrationRule {
+    pub fn new(first: usize, last: usize, base: u32, combine: Combine) -> CalibrationRule {
+        assert!((2..=36).contains(&base), "base must be within 2..=36");
+
+        CalibrationRule {
+            first,
+            last,
+            base,
+            combine,
         }
     }
 
-    Some(
-        format!(
-            "{}{}",
-            all_numbers.first().unwrap(),
-            all_numbers.last().unwrap()
+    pub fn first(&self) -> usize {
+        self.first
+    }
+
+    pub fn last(&self) -> usize {
+        self.last
+    }
+
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    pub fn combine(&self) -> Combine {
+        self.combine
+    }
+
+    /// Returns all digits of the line which are valid in the rule's base, e.g. 'a'..'f' for base 16.
+    pub fn digits_in_line(&self, line: &str) -> Vec<usize> {
+        line.chars()
+            .filter_map(|c| c.to_digit(self.base))
+            .map(|digit| digit as usize)
+            .collect()
+    }
+
+    /// Takes the first `first` and the last `last` digits and combines them.
+    /// The two selections may overlap, so a single digit '8' yields 88 with the default rule.
+    /// Returns `None` if there are not enough digits, a digit is not valid in the rule's base
+    /// or the value does not fit into an usize.
+    pub fn apply(&self, all_numbers: &[usize]) -> Option<usize> {
+        if all_numbers.len() < self.first.max(self.last) || self.first + self.last == 0 {
+            return None;
+        }
+        if all_numbers.iter().any(|digit| *digit >= self.base as usize) {
+            return None;
+        }
+
+        let mut selected = all_numbers[..self.first]
+            .iter()
+            .chain(all_numbers[all_numbers.len() - self.last..].iter());
+
+        match self.combine {
+            Combine::Concat => selected.try_fold(0usize, |acc, digit| {
+                acc.checked_mul(self.base as usize)?.checked_add(*digit)
+            }),
+            Combine::Sum => selected.try_fold(0usize, |acc, digit| acc.checked_add(*digit)),
+            Combine::Product => selected.try_fold(1usize, |acc, digit| acc.checked_mul(*digit)),
+        }
+    }
+}
+
+pub fn extract_calibration_number(all_numbers: Vec<usize>) -> Option<usize> {
+    CalibrationRule::default().apply(&all_numbers)
+}
+
+/// Returned if a line does not yield a calibration value. `line_number` starts at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalibrationError {
+    pub line_number: usize,
+    pub line: String,
+}
+
+impl Display for CalibrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "cannot extract calibration value in line {}: '{}'",
+            self.line_number, self.line
         )
-        .parse::<usize>()
-        .unwrap(),
-    )
+    }
+}
+
+impl Error for CalibrationError {}
+
+/// Calibration value of a single line, `None` if the line does not hold enough numbers.
+/// With `extended` set, teens, tens and compound number words are recognized as well.
+///
+/// Number words are decimal, so they are only recognized with base 10.
+/// In any other base the digits of the base are taken, e.g. 'a'..'f' for base 16.
+pub fn calibration_value(line: &str, rule: &CalibrationRule, extended: bool) -> Option<usize> {
+    let numbers = if rule.base != 10 {
+        rule.digits_in_line(line)
+    } else if extended {
+        words::extended_numbers_in_line(line).ok()?
+    } else {
+        numbers_in_line(&replace_number_strings(line), &STRING_NUMBER_REGEX).ok()?
+    };
+
+    rule.apply(&numbers)
+}
+
+/// Sums the calibration values of all lines, failing on the first line without a value.
+pub fn sum_calibration_values(
+    input: &str,
+    rule: &CalibrationRule,
+    extended: bool,
+) -> Result<usize, CalibrationError> {
+    let mut sum: usize = 0;
+    for (idx, line) in input.lines().enumerate() {
+        match calibration_value(line, rule, extended) {
+            Some(value) => sum += value,
+            None => {
+                return Err(CalibrationError {
+                    line_number: idx + 1,
+                    line: line.to_string(),
+                })
+            }
+        }
+    }
+
+    Ok(sum)
+}
+
+/// Splits the input into chunks of roughly `chunk_size` bytes, each ending on a line break.
+/// Chunks never split a line, so a line longer than `chunk_size` ends up in a chunk of its own.
+pub fn line_chunks(input: &str, chunk_size: usize) -> Vec<&str> {
+    let mut chunks: Vec<&str> = vec![];
+
+    let mut rest = input;
+    while !rest.is_empty() {
+        // a '\n' byte is never part of a multibyte char, so searching the bytes is safe
+        let start = chunk_size.max(1).min(rest.len());
+        let split_at = rest.as_bytes()[start..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(rest.len(), |pos| start + pos + 1);
+
+        let (chunk, remainder) = rest.split_at(split_at);
+        chunks.push(chunk);
+        rest = remainder;
+    }
+
+    chunks
+}
+
+/// Same as `sum_calibration_values`, but the input is split into line-aligned chunks of about `chunk_size` bytes
+/// which are processed on the rayon thread pool. The result, including the reported error, is identical.
+pub fn par_sum_calibration_values(
+    input: &str,
+    rule: &CalibrationRule,
+    extended: bool,
+    chunk_size: usize,
+) -> Result<usize, CalibrationError> {
+    let chunks = line_chunks(input, chunk_size);
+
+    // collecting keeps the order of the chunks, so the first error is the one of the sequential path
+    let results: Vec<Result<usize, CalibrationError>> = chunks
+        .par_iter()
+        .map(|chunk| sum_calibration_values(chunk, rule, extended))
+        .collect();
+
+    let mut sum: usize = 0;
+    let mut lines_before: usize = 0;
+    for (chunk, result) in chunks.iter().zip(results) {
+        match result {
+            Ok(chunk_sum) => sum += chunk_sum,
+            Err(mut e) => {
+                e.line_number += lines_before;
+                return Err(e);
+            }
+        }
+        lines_before += chunk.lines().count();
+    }
+
+    Ok(sum)
 }
 
 #[cfg(test)]
@@ -98,6 +293,69 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_calibration_rule() {
+        let rule = CalibrationRule::new(2, 1, 10, Combine::Concat);
+        assert_eq!(rule.apply(&[1, 2, 3, 4]).unwrap(), 124);
+        assert_eq!(rule.apply(&[7]), None);
+
+        let hex = CalibrationRule::new(1, 1, 16, Combine::Concat);
+        let digits = hex.digits_in_line("xxaz9zzf");
+        assert_eq!(digits, vec![10, 9, 15]);
+        assert_eq!(hex.apply(&digits).unwrap(), 0xaf);
+        assert_eq!(calibration_value("xxaz9zzf", &hex, false), Some(0xaf));
+        assert_eq!(calibration_value("xxaz9zzf", &hex, true), Some(0xaf));
+
+        // digits which are not valid in the base are rejected, not concatenated
+        let binary = CalibrationRule::new(1, 1, 2, Combine::Concat);
+        assert_eq!(binary.apply(&[1, 9]), None);
+        assert_eq!(calibration_value("1abc9", &binary, false), Some(0b11));
+
+        let sum = CalibrationRule::new(1, 2, 10, Combine::Sum);
+        assert_eq!(sum.apply(&[1, 2, 3, 4]).unwrap(), 8);
+
+        let product = CalibrationRule::new(1, 1, 10, Combine::Product);
+        assert_eq!(product.apply(&[2, 5, 3]).unwrap(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "base must be within 2..=36")]
+    fn test_calibration_rule_base_out_of_range() {
+        CalibrationRule::new(1, 1, 40, Combine::Concat);
+    }
+
+    #[test]
+    fn test_par_sum_calibration_values() {
+        let input =
+            "1abc2\npqr3stu8vwx\na1b2c3d4e5f\ntreb7uchet\ntwo1nine\neightwothree\n".repeat(50);
+        let rule = CalibrationRule::default();
+
+        let sequential = sum_calibration_values(&input, &rule, false).unwrap();
+        for chunk_size in [1, 7, 64, 1000, 1 << 20] {
+            assert_eq!(
+                par_sum_calibration_values(&input, &rule, false, chunk_size).unwrap(),
+                sequential
+            );
+        }
+
+        let broken = format!("{input}nothing\n{input}");
+        let expected = sum_calibration_values(&broken, &rule, false).unwrap_err();
+        assert_eq!(expected.line_number, 301);
+        assert_eq!(
+            par_sum_calibration_values(&broken, &rule, false, 16).unwrap_err(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_line_chunks() {
+        let input = "aa\nbbbb\nc\nd";
+        assert_eq!(line_chunks(input, 1), vec!["aa\n", "bbbb\n", "c\n", "d"]);
+        assert_eq!(line_chunks(input, 4), vec!["aa\nbbbb\n", "c\nd"]);
+        assert_eq!(line_chunks(input, 100), vec![input]);
+        assert_eq!(line_chunks("äö\nü", 1), vec!["äö\n", "ü"]);
+    }
+
     #[test]
     fn test_string_numbers_in_line() {
         assert_eq!(
@@ -1,16 +1,38 @@
 use day_01::{
-    extract_calibration_number, numbers_in_line, replace_number_strings, STRING_NUMBER_REGEX,
+    extract_calibration_number, numbers_in_line, par_sum_calibration_values,
+    replace_number_strings, words::extended_numbers_in_line, CalibrationRule, STRING_NUMBER_REGEX,
 };
 
+// Size of the line-aligned chunks which are handed to the thread pool in '--parallel' mode
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
 fn main() {
     let input_filename = "input4";
     let input = std::fs::read_to_string(input_filename).unwrap();
+    // with '--extended' also teens, tens and compound numbers like "forty-two" are recognized
+    let extended = std::env::args().any(|arg| arg == "--extended");
+
+    // with '--parallel' the lines are processed on all cores and only the result is printed
+    if std::env::args().any(|arg| arg == "--parallel") {
+        match par_sum_calibration_values(&input, &CalibrationRule::default(), extended, CHUNK_SIZE)
+        {
+            Ok(result) => println! {"=> The result is: {:?}", result},
+            Err(e) => panic!("{e}"),
+        }
+        return;
+    }
+
     let mut line_numbers: Vec<usize> = vec![];
 
     for line in input.lines() {
         let line_str = replace_number_strings(line);
         println! {"{:?}", line_str};
-        match numbers_in_line(&line_str.to_string(), &STRING_NUMBER_REGEX) {
+        let numbers = if extended {
+            extended_numbers_in_line(line)
+        } else {
+            numbers_in_line(&line_str, &STRING_NUMBER_REGEX)
+        };
+        match numbers {
             Ok(numbers) => {
                 if let Some(calibration_number) = extract_calibration_number(numbers) {
                     println! {"{line_str}: {calibration_number}"};
@@ -23,7 +45,7 @@ fn main() {
         }
     }
 
-    let result: usize = line_numbers.iter().map(|number| number).sum();
+    let result: usize = line_numbers.iter().sum();
 
     println! {"\n=> The result is: {:?}", result};
 }
@@ -0,0 +1,229 @@
+use std::error::Error;
+
+// Every number word which can be part of a compound number like "one thousand two hundred and forty-two"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Word {
+    Unit(u64),
+    Teen(u64),
+    Tens(u64),
+    Hundred,
+    Thousand,
+    And,
+}
+
+const LEXICON: [(&str, Word); 31] = [
+    ("zero", Word::Unit(0)),
+    ("one", Word::Unit(1)),
+    ("two", Word::Unit(2)),
+    ("three", Word::Unit(3)),
+    ("four", Word::Unit(4)),
+    ("five", Word::Unit(5)),
+    ("six", Word::Unit(6)),
+    ("seven", Word::Unit(7)),
+    ("eight", Word::Unit(8)),
+    ("nine", Word::Unit(9)),
+    ("ten", Word::Teen(10)),
+    ("eleven", Word::Teen(11)),
+    ("twelve", Word::Teen(12)),
+    ("thirteen", Word::Teen(13)),
+    ("fourteen", Word::Teen(14)),
+    ("fifteen", Word::Teen(15)),
+    ("sixteen", Word::Teen(16)),
+    ("seventeen", Word::Teen(17)),
+    ("eighteen", Word::Teen(18)),
+    ("nineteen", Word::Teen(19)),
+    ("twenty", Word::Tens(20)),
+    ("thirty", Word::Tens(30)),
+    ("forty", Word::Tens(40)),
+    ("fifty", Word::Tens(50)),
+    ("sixty", Word::Tens(60)),
+    ("seventy", Word::Tens(70)),
+    ("eighty", Word::Tens(80)),
+    ("ninety", Word::Tens(90)),
+    ("hundred", Word::Hundred),
+    ("thousand", Word::Thousand),
+    ("and", Word::And),
+];
+
+/// A number found in a line, either a single digit or a (compound) number word.
+/// `start..end` is the byte range of the token within the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberToken {
+    pub value: u64,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl NumberToken {
+    /// The digits this token contributes to the calibration value.
+    /// Compound numbers contribute all of their decimal digits: "forty-two" -> [4, 2], "one hundred" -> [1, 0, 0]
+    pub fn digits(&self) -> Vec<usize> {
+        self.value
+            .to_string()
+            .chars()
+            .map(|c| c.to_digit(10).unwrap() as usize)
+            .collect()
+    }
+}
+
+// Returns the longest word of the lexicon starting at `pos`, so "seventeen" wins over "seven".
+fn word_at(input: &[u8], pos: usize) -> Option<(Word, usize)> {
+    LEXICON
+        .iter()
+        .filter(|(word, _)| input[pos..].starts_with(word.as_bytes()))
+        .max_by_key(|(word, _)| word.len())
+        .map(|(word, kind)| (*kind, pos + word.len()))
+}
+
+// Words of a compound number may be separated by spaces or hyphens, or not at all
+fn skip_separators(input: &[u8], mut pos: usize) -> usize {
+    while pos < input.len() && (input[pos] == b' ' || input[pos] == b'-') {
+        pos += 1;
+    }
+    pos
+}
+
+fn below_hundred(input: &[u8], pos: usize) -> Option<(u64, usize)> {
+    match word_at(input, pos)? {
+        (Word::Unit(value), end) | (Word::Teen(value), end) => Some((value, end)),
+        (Word::Tens(tens), end) => match word_at(input, skip_separators(input, end)) {
+            Some((Word::Unit(unit), unit_end)) if unit > 0 => Some((tens + unit, unit_end)),
+            _ => Some((tens, end)),
+        },
+        _ => None,
+    }
+}
+
+// An optional "and" may follow "hundred" or "thousand", e.g. "three hundred and five"
+fn skip_and(input: &[u8], pos: usize) -> usize {
+    let next = skip_separators(input, pos);
+    match word_at(input, next) {
+        Some((Word::And, end)) => skip_separators(input, end),
+        _ => next,
+    }
+}
+
+fn below_thousand(input: &[u8], pos: usize) -> Option<(u64, usize)> {
+    if let Some((Word::Unit(hundreds), end)) = word_at(input, pos) {
+        // "zero hundred" is not a number, but "zero" still is
+        if hundreds > 0 {
+            if let Some((Word::Hundred, end)) = word_at(input, skip_separators(input, end)) {
+                return match below_hundred(input, skip_and(input, end)) {
+                    Some((rest, rest_end)) if rest > 0 => Some((hundreds * 100 + rest, rest_end)),
+                    _ => Some((hundreds * 100, end)),
+                };
+            }
+        }
+    }
+
+    below_hundred(input, pos)
+}
+
+// phrase := below_thousand [ "thousand" [ "and" ] [ below_thousand ] ]
+fn phrase(input: &[u8], pos: usize) -> Option<(u64, usize)> {
+    let (mut value, mut end) = below_thousand(input, pos)?;
+
+    if let Some((Word::Thousand, thousand_end)) = word_at(input, skip_separators(input, end)) {
+        value *= 1000;
+        end = thousand_end;
+
+        if let Some((rest, rest_end)) = below_thousand(input, skip_and(input, end)) {
+            value += rest;
+            end = rest_end;
+        }
+    }
+
+    Some((value, end))
+}
+
+/// Finds all digits and number words in the line, including teens, tens and compound numbers.
+///
+/// At every position the longest number phrase is taken ("seventeen" is 17, not 7).
+/// Scanning resumes on the last character of a phrase, so overlapping words like "eightwo" still yield 8 and 2,
+/// just like part 2 of the puzzle expects.
+pub fn tokenize(line: &str) -> Vec<NumberToken> {
+    let lowered = line.to_ascii_lowercase();
+    let input = lowered.as_bytes();
+
+    let mut tokens: Vec<NumberToken> = vec![];
+    let mut pos = 0;
+    while pos < input.len() {
+        if input[pos].is_ascii_digit() {
+            tokens.push(NumberToken {
+                value: (input[pos] - b'0') as u64,
+                start: pos,
+                end: pos + 1,
+            });
+            pos += 1;
+            continue;
+        }
+
+        match phrase(input, pos) {
+            Some((value, end)) => {
+                tokens.push(NumberToken {
+                    value,
+                    start: pos,
+                    end,
+                });
+                pos = end - 1;
+            }
+            None => pos += 1,
+        }
+    }
+
+    tokens
+}
+
+/// Same as `numbers_in_line`, but using the extended number words of `tokenize`.
+pub fn extended_numbers_in_line(line: &str) -> Result<Vec<usize>, Box<dyn Error>> {
+    let all_numbers: Vec<usize> = tokenize(line)
+        .iter()
+        .flat_map(|token| token.digits())
+        .collect();
+
+    if all_numbers.is_empty() {
+        return Err("no numbers found".into());
+    }
+
+    Ok(all_numbers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(line: &str) -> Vec<u64> {
+        tokenize(line).iter().map(|token| token.value).collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(values("eleven"), vec![11]);
+        assert_eq!(values("xtwentyx"), vec![20]);
+        assert_eq!(values("forty-two"), vec![42]);
+        assert_eq!(values("fortytwo"), vec![42]);
+        assert_eq!(values("one hundred"), vec![100]);
+        assert_eq!(values("three hundred and five"), vec![305]);
+        assert_eq!(values("two thousand twenty-three"), vec![2023]);
+        assert_eq!(values("eightwo3seventeen"), vec![8, 2, 3, 17]);
+        assert_eq!(values("ninety nine"), vec![99]);
+        assert_eq!(values("one and two"), vec![1, 2]);
+        assert_eq!(values("zero hundred"), vec![0]);
+        assert_eq!(values("one thousand and five"), vec![1005]);
+        assert_eq!(values("one thousand five"), vec![1005]);
+        assert_eq!(values("one thousand and"), vec![1000]);
+    }
+
+    #[test]
+    fn test_extended_numbers_in_line() {
+        assert_eq!(
+            extended_numbers_in_line("a1forty-two").unwrap(),
+            vec![1, 4, 2]
+        );
+        assert_eq!(
+            extended_numbers_in_line("one hundred").unwrap(),
+            vec![1, 0, 0]
+        );
+        assert!(extended_numbers_in_line("nothing here").is_err());
+    }
+}
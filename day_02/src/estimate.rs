@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+
+use crate::{CubeSet, Draw, Game};
+
+// Half of the 95% quantile of the chi-squared distribution with one degree of freedom (3.841 / 2)
+const LOG_LIKELIHOOD_DROP_95: f64 = 1.920_729;
+
+/// The most likely bag contents given a number of draws.
+///
+/// Every draw is assumed to be taken without replacement from the same bag, and the cubes are put
+/// back before the next draw. The probability of a single draw is then the multivariate hypergeometric
+/// probability `prod(C(N_c, k_c)) / C(N, n)` where `N_c` is the number of cubes of a color in the bag,
+/// `k_c` the number of cubes drawn of that color and `N`, `n` the respective totals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BagEstimate {
+    pub bag: CubeSet,
+    /// Natural logarithm of the likelihood of all draws given `bag`
+    pub log_likelihood: f64,
+    /// Inclusive 95% profile likelihood interval of the count of each color
+    pub intervals: BTreeMap<String, (u32, u32)>,
+    /// The estimate or one of the intervals reaches `max_count`. The likelihood often keeps growing
+    /// for larger bags with the same proportions, so the draws alone cannot tell the bag size apart.
+    pub at_search_limit: bool,
+}
+
+// ln(n!) for all n up to the given maximum
+fn ln_factorials(max: usize) -> Vec<f64> {
+    let mut table = vec![0.0; max + 1];
+    for n in 1..=max {
+        table[n] = table[n - 1] + (n as f64).ln();
+    }
+    table
+}
+
+fn ln_binomial(ln_fact: &[f64], n: usize, k: usize) -> f64 {
+    ln_fact[n] - ln_fact[k] - ln_fact[n - k]
+}
+
+// Combines the terms of the colors one at a time. best[t] is the best sum of the color terms of all bags
+// with t cubes in total, choices[i][t] is the count of the i-th color in that bag, given the colors before it.
+fn combine(color_terms: &[&Vec<f64>]) -> (Vec<f64>, Vec<Vec<usize>>) {
+    let mut best: Vec<f64> = vec![0.0];
+    let mut choices: Vec<Vec<usize>> = vec![];
+    for terms in color_terms {
+        let mut next = vec![f64::NEG_INFINITY; best.len() + terms.len() - 1];
+        let mut choice = vec![0; next.len()];
+        for (rest, rest_value) in best.iter().enumerate() {
+            if *rest_value == f64::NEG_INFINITY {
+                continue;
+            }
+            for (n, value) in terms.iter().enumerate() {
+                if rest_value + value > next[rest + n] {
+                    next[rest + n] = rest_value + value;
+                    choice[rest + n] = n;
+                }
+            }
+        }
+        best = next;
+        choices.push(choice);
+    }
+    (best, choices)
+}
+
+/// Estimates the bag among all bags with at most `max_count` cubes per color.
+///
+/// Only colors which occur in the draws are considered. Instead of evaluating every bag, the colors are
+/// combined one at a time for every total, so the runtime grows with `max_count` squared and the number
+/// of colors cubed, not with `max_count` to the power of the number of colors.
+/// Returns `None` if there are no colors, or a draw needs more than `max_count` cubes of a color.
+pub fn estimate_bag<'a>(
+    draws: impl IntoIterator<Item = &'a Draw>,
+    max_count: u32,
+) -> Option<BagEstimate> {
+    let draws: Vec<&Draw> = draws.into_iter().collect();
+    let minimum = draws
+        .iter()
+        .fold(CubeSet::new(), |acc, draw| acc.max(&draw.cubes));
+    let colors: Vec<&str> = minimum.colors().collect();
+    if colors.is_empty() || minimum.iter().any(|(_, count)| count > max_count) {
+        return None;
+    }
+
+    let max_count = max_count as usize;
+    let ln_fact = ln_factorials(max_count * colors.len());
+
+    // The log likelihood splits into a sum over colors, which only depends on the count of that color,
+    // and the denominator, which only depends on the total. Both are tabulated upfront.
+    let color_terms: Vec<Vec<f64>> = colors
+        .iter()
+        .map(|color| {
+            (0..=max_count)
+                .map(|n| {
+                    draws
+                        .iter()
+                        .map(|draw| draw.cubes.get(color) as usize)
+                        .map(|k| {
+                            if k <= n {
+                                ln_binomial(&ln_fact, n, k)
+                            } else {
+                                f64::NEG_INFINITY
+                            }
+                        })
+                        .sum()
+                })
+                .collect()
+        })
+        .collect();
+    let draw_sizes: Vec<usize> = draws
+        .iter()
+        .map(|draw| draw.cubes.iter().map(|(_, count)| count as usize).sum())
+        .collect();
+    let total_terms: Vec<f64> = (0..=max_count * colors.len())
+        .map(|total| {
+            draw_sizes
+                .iter()
+                .map(|n| {
+                    if *n <= total {
+                        ln_binomial(&ln_fact, total, *n)
+                    } else {
+                        f64::INFINITY
+                    }
+                })
+                .sum()
+        })
+        .collect();
+
+    let lower: Vec<usize> = colors
+        .iter()
+        .map(|color| minimum.get(color) as usize)
+        .collect();
+
+    // the best bag: the best combination of all colors for every total, and the best total
+    let all_terms: Vec<&Vec<f64>> = color_terms.iter().collect();
+    let (best, choices) = combine(&all_terms);
+    let mut best_total: Option<(f64, usize)> = None;
+    for (total, value) in best.iter().enumerate() {
+        let log_likelihood = value - total_terms[total];
+        if best_total
+            .as_ref()
+            .is_none_or(|(best, _)| log_likelihood > *best)
+        {
+            best_total = Some((log_likelihood, total));
+        }
+    }
+    let (log_likelihood, mut total) = best_total?;
+    if log_likelihood == f64::NEG_INFINITY {
+        return None;
+    }
+    let mut counts: Vec<usize> = vec![0; colors.len()];
+    for c in (0..colors.len()).rev() {
+        counts[c] = choices[c][total];
+        total -= counts[c];
+    }
+
+    // profile[c][n] is the best log likelihood of all bags with n cubes of color c,
+    // combining the other colors for every total
+    let mut profile: Vec<Vec<f64>> = vec![vec![f64::NEG_INFINITY; max_count + 1]; colors.len()];
+    for (c, terms) in color_terms.iter().enumerate() {
+        let others: Vec<&Vec<f64>> = color_terms
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != c)
+            .map(|(_, terms)| terms)
+            .collect();
+        let (others_best, _) = combine(&others);
+        for (n, value) in terms.iter().enumerate() {
+            for (rest, rest_value) in others_best.iter().enumerate() {
+                let log_likelihood = value + rest_value - total_terms[n + rest];
+                profile[c][n] = profile[c][n].max(log_likelihood);
+            }
+        }
+    }
+
+    let mut intervals: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+    for (c, color) in colors.iter().enumerate() {
+        let within: Vec<usize> = (lower[c]..=max_count)
+            .filter(|n| profile[c][*n] >= log_likelihood - LOG_LIKELIHOOD_DROP_95)
+            .collect();
+        intervals.insert(
+            color.to_string(),
+            (*within.first()? as u32, *within.last()? as u32),
+        );
+    }
+
+    let at_search_limit = counts.contains(&max_count)
+        || intervals
+            .values()
+            .any(|(_, high)| *high as usize == max_count);
+
+    Some(BagEstimate {
+        bag: colors
+            .iter()
+            .zip(counts.iter())
+            .map(|(color, n)| (*color, *n as u32))
+            .collect(),
+        log_likelihood,
+        intervals,
+        at_search_limit,
+    })
+}
+
+/// Estimates the bag of a single game from its draws.
+pub fn estimate_game(game: &Game, max_count: u32) -> Option<BagEstimate> {
+    estimate_bag(&game.draws, max_count)
+}
+
+/// Estimates a single bag which is shared by all games, using the draws of every game.
+pub fn estimate_all(games: &[Game], max_count: u32) -> Option<BagEstimate> {
+    estimate_bag(games.iter().flat_map(|game| game.draws.iter()), max_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_games;
+
+    #[test]
+    fn test_estimate_bag() {
+        // A single draw of everything in the bag is certain for the minimum bag
+        let games = parse_games("Game 1: 3 red, 1 blue").unwrap();
+        let estimate = estimate_game(&games[0], 10).unwrap();
+        assert_eq!(estimate.bag, "3 red, 1 blue".parse().unwrap());
+        assert!(estimate.log_likelihood.abs() < 1e-9);
+
+        // The minimum bag explains these draws best
+        let games = parse_games("Game 1: 1 red, 1 blue; 1 red, 1 blue; 2 red").unwrap();
+        let estimate = estimate_game(&games[0], 30).unwrap();
+        assert_eq!(estimate.bag, "2 red, 1 blue".parse().unwrap());
+        assert_eq!(estimate.intervals["red"].0, 2);
+        // three draws are not enough to rule out much larger bags
+        assert!(estimate.at_search_limit);
+
+        // Drawing 3 red at once and then a mix is more likely from a larger bag
+        let games = parse_games(&"Game 1: 3 red; 1 red, 1 blue\n".repeat(5)).unwrap();
+        let estimate = estimate_all(&games, 20).unwrap();
+        assert!(estimate.bag.get("red") > 3);
+        let (low, high) = estimate.intervals["red"];
+        assert!(low <= estimate.bag.get("red") && estimate.bag.get("red") <= high);
+
+        assert_eq!(estimate_game(&games[0], 1), None);
+    }
+
+    // The log likelihood of the draws given the bag, straight from the formula
+    fn log_likelihood(draws: &[Draw], bag: &[(&str, usize)]) -> f64 {
+        let ln_fact = ln_factorials(100);
+        let total: usize = bag.iter().map(|(_, n)| n).sum();
+        draws
+            .iter()
+            .map(|draw| {
+                let drawn: usize = draw.cubes.iter().map(|(_, k)| k as usize).sum();
+                if drawn > total {
+                    return f64::NEG_INFINITY;
+                }
+                bag.iter()
+                    .map(|(color, n)| match draw.cubes.get(color) as usize {
+                        k if k <= *n => ln_binomial(&ln_fact, *n, k),
+                        _ => f64::NEG_INFINITY,
+                    })
+                    .sum::<f64>()
+                    - ln_binomial(&ln_fact, total, drawn)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_estimate_matches_exhaustive_search() {
+        let log = "Game 1: 1 red, 2 green; 3 blue; 2 red, 1 green, 1 blue\n\
+                   Game 2: 4 green; 1 red, 1 blue\n\
+                   Game 3: 2 blue, 2 green; 2 red, 2 green, 2 blue";
+        let max_count = 9;
+        for game in parse_games(log).unwrap() {
+            let estimate = estimate_game(&game, max_count as u32).unwrap();
+
+            // every bag of the three colors, like the search before the colors were combined
+            let mut best = f64::NEG_INFINITY;
+            let mut profile: BTreeMap<(&str, usize), f64> = BTreeMap::new();
+            for red in 0..=max_count {
+                for green in 0..=max_count {
+                    for blue in 0..=max_count {
+                        let bag = [("red", red), ("green", green), ("blue", blue)];
+                        let value = log_likelihood(&game.draws, &bag);
+                        best = best.max(value);
+                        for (color, n) in bag {
+                            let entry = profile.entry((color, n)).or_insert(f64::NEG_INFINITY);
+                            *entry = entry.max(value);
+                        }
+                    }
+                }
+            }
+
+            let bag: Vec<(&str, usize)> = ["red", "green", "blue"]
+                .map(|color| (color, estimate.bag.get(color) as usize))
+                .to_vec();
+            assert!((estimate.log_likelihood - best).abs() < 1e-9);
+            assert!((log_likelihood(&game.draws, &bag) - best).abs() < 1e-9);
+            for (color, (low, high)) in &estimate.intervals {
+                let within: Vec<usize> = (0..=max_count)
+                    .filter(|n| profile[&(color.as_str(), *n)] >= best - LOG_LIKELIHOOD_DROP_95)
+                    .collect();
+                assert_eq!(
+                    (*low as usize, *high as usize),
+                    (within[0], *within.last().unwrap())
+                );
+            }
+        }
+    }
+}
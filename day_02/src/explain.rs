@@ -0,0 +1,143 @@
+use std::fmt::{self, Display};
+
+use serde::Serialize;
+
+use crate::{log_colors, CubeSet, Game, Violation};
+
+/// The maximum count of a color within a game and the first draw which showed it.
+/// A color the game never shows has a count of 0 and no draw.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColorMaximum {
+    pub color: String,
+    pub count: u32,
+    pub draw_index: Option<usize>,
+}
+
+/// Why a game is (im)possible with a bag (part 1) and how its power is computed (part 2).
+/// Draw indices start at 0, the text output counts draws starting at 1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GameExplanation {
+    pub game_id: u32,
+    pub possible: bool,
+    pub violation: Option<Violation>,
+    pub maxima: Vec<ColorMaximum>,
+    pub power: Option<u128>,
+}
+
+impl GameExplanation {
+    /// `colors` are the colors the power is taken over, usually all colors of the log.
+    pub fn new(game: &Game, bag: &CubeSet, colors: &[String]) -> GameExplanation {
+        let violation = game.first_violation(bag);
+
+        let mut maxima: Vec<ColorMaximum> = vec![];
+        let minimum_bag = game.minimum_bag();
+        for color in colors {
+            let count = minimum_bag.get(color);
+            let draw_index = game
+                .draws
+                .iter()
+                .position(|draw| count > 0 && draw.cubes.get(color) == count);
+            maxima.push(ColorMaximum {
+                color: color.to_string(),
+                count,
+                draw_index,
+            });
+        }
+
+        GameExplanation {
+            game_id: game.id,
+            possible: violation.is_none(),
+            violation,
+            maxima,
+            power: minimum_bag.power(colors.iter().map(|color| color.as_str())),
+        }
+    }
+
+    /// The power as a formula, e.g. "6 * 2 * 4 = 48"
+    pub fn power_formula(&self) -> String {
+        let factors: Vec<String> = self
+            .maxima
+            .iter()
+            .map(|maximum| maximum.count.to_string())
+            .collect();
+        match self.power {
+            Some(power) => format!("{} = {power}", factors.join(" * ")),
+            None => format!("{} does not fit into an u128", factors.join(" * ")),
+        }
+    }
+}
+
+impl Display for GameExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.violation {
+            None => writeln!(f, "Game #{} is possible", self.game_id)?,
+            Some(violation) => writeln!(
+                f,
+                "Game #{} is impossible: draw #{} has {} {}, but the bag only holds {}",
+                self.game_id,
+                violation.draw_index + 1,
+                violation.count,
+                violation.color,
+                violation.available
+            )?,
+        }
+        for maximum in &self.maxima {
+            match maximum.draw_index {
+                Some(draw_index) => writeln!(
+                    f,
+                    "  at least {} {} are needed because of draw #{}",
+                    maximum.count,
+                    maximum.color,
+                    draw_index + 1
+                )?,
+                None => writeln!(f, "  no draw shows {}, so 0 are needed", maximum.color)?,
+            }
+        }
+        write!(f, "  power: {}", self.power_formula())
+    }
+}
+
+/// Explains every game, with the powers taken over all colors of the log.
+pub fn explain(games: &[Game], bag: &CubeSet) -> Vec<GameExplanation> {
+    let colors = log_colors(games);
+    games
+        .iter()
+        .map(|game| GameExplanation::new(game, bag, &colors))
+        .collect()
+}
+
+pub fn to_json(explanations: &[GameExplanation]) -> String {
+    serde_json::to_string_pretty(explanations).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_games;
+
+    #[test]
+    fn test_explain() {
+        let games =
+            parse_games("Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red")
+                .unwrap();
+        let bag: CubeSet = "12 red, 13 green, 14 blue".parse().unwrap();
+
+        let explanations = explain(&games, &bag);
+        let explanation = &explanations[0];
+        assert!(!explanation.possible);
+        assert_eq!(explanation.violation.as_ref().unwrap().color, "red");
+        assert_eq!(
+            explanation.maxima[1],
+            ColorMaximum {
+                color: "green".to_string(),
+                count: 13,
+                draw_index: Some(1)
+            }
+        );
+        assert_eq!(explanation.power_formula(), "6 * 13 * 20 = 1560");
+
+        let json: serde_json::Value = serde_json::from_str(&to_json(&explanations)).unwrap();
+        assert_eq!(json[0]["violation"]["draw_index"], 0);
+        assert_eq!(json[0]["power"], 1560);
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+
+pub mod estimate;
+pub mod explain;
+pub mod query;
+pub mod stats;
+pub mod validate;
+
+lazy_static! {
+    pub static ref MATCH_COLORS_REGEX: Regex =
+        Regex::new(r"(?P<count>\d+)\s(?P<color>\w+)(,\s)?").unwrap();
+    // a whole set, e.g. "12 red, 13 green, 14 blue", with nothing else around it
+    pub static ref CUBE_SET_REGEX: Regex =
+        Regex::new(r"^\s*\d+\s\w+(\s*,\s*\d+\s\w+)*\s*$").unwrap();
+    pub static ref GAME_REGEX: Regex =
+        Regex::new(r"Game\s(?P<game_id>\d+):\s(?P<draws>[\w\d\s,;]+)$").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseGameError {
+    /// The line does not look like "Game <id>: <draws>"
+    InvalidFormat(String),
+    /// The game id does not fit into an u32
+    IdOutOfRange(String),
+    /// A cube count does not fit into an u32
+    CountOutOfRange(String),
+}
+
+impl Display for ParseGameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseGameError::InvalidFormat(line) => write!(f, "not a game: '{line}'"),
+            ParseGameError::IdOutOfRange(id) => write!(f, "game id {id} is out of range"),
+            ParseGameError::CountOutOfRange(count) => {
+                write!(f, "cube count {count} is out of range")
+            }
+        }
+    }
+}
+
+impl Error for ParseGameError {}
+
+/// A `ParseGameError` together with the line of the game log it occurred in. `line_number` starts at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameLogError {
+    pub line_number: usize,
+    pub line: String,
+    pub error: ParseGameError,
+}
+
+impl Display for GameLogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {} in '{}'",
+            self.line_number, self.error, self.line
+        )
+    }
+}
+
+impl Error for GameLogError {}
+
+/// A number of cubes per color, e.g. "3 blue, 4 red". Colors which are not present have a count of 0.
+///
+/// The colors are kept sorted by name, so iterating over a set is deterministic.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CubeSet {
+    counts: BTreeMap<String, u32>,
+}
+
+impl CubeSet {
+    pub fn new() -> CubeSet {
+        CubeSet::default()
+    }
+
+    pub fn get(&self, color: &str) -> u32 {
+        self.counts.get(color).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, color: &str, count: u32) {
+        self.counts.insert(color.to_string(), count);
+    }
+
+    pub fn colors(&self) -> impl Iterator<Item = &str> {
+        self.counts.keys().map(|color| color.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.counts
+            .iter()
+            .map(|(color, count)| (color.as_str(), *count))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// True if every color of `other` fits into this set, e.g. a draw into a bag.
+    pub fn contains(&self, other: &CubeSet) -> bool {
+        other.iter().all(|(color, count)| count <= self.get(color))
+    }
+
+    /// The per-color maximum of both sets.
+    pub fn max(&self, other: &CubeSet) -> CubeSet {
+        let mut result = self.clone();
+        for (color, count) in other.iter() {
+            result.set(color, count.max(self.get(color)));
+        }
+        result
+    }
+
+    /// Product of the counts of the given colors, `None` if it does not fit into an u128.
+    ///
+    /// Colors which are not in the set count as 0, so a game which never shows one of the colors
+    /// has a power of 0. Use `log_colors` for the colors of the whole log.
+    pub fn power<'a>(&self, colors: impl IntoIterator<Item = &'a str>) -> Option<u128> {
+        colors
+            .into_iter()
+            .try_fold(1u128, |acc, color| acc.checked_mul(self.get(color) as u128))
+    }
+}
+
+impl Display for CubeSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cubes: Vec<String> = self
+            .iter()
+            .map(|(color, count)| format!("{count} {color}"))
+            .collect();
+        write!(f, "{}", cubes.join(", "))
+    }
+}
+
+/// Parses a set in the same notation as a draw, e.g. "12 red, 13 green, 14 blue".
+impl FromStr for CubeSet {
+    type Err = ParseGameError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if !CUBE_SET_REGEX.is_match(input) {
+            return Err(ParseGameError::InvalidFormat(input.to_string()));
+        }
+        Ok(draw_from_string(input)?.cubes)
+    }
+}
+
+impl<'a> FromIterator<(&'a str, u32)> for CubeSet {
+    fn from_iter<T: IntoIterator<Item = (&'a str, u32)>>(iter: T) -> Self {
+        let mut set = CubeSet::new();
+        for (color, count) in iter {
+            set.set(color, count);
+        }
+        set
+    }
+}
+
+/// A single handful of cubes shown during a game.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Draw {
+    pub cubes: CubeSet,
+}
+
+/// The first color of a draw which does not fit into a bag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    /// Index of the draw within the game, starting at 0
+    pub draw_index: usize,
+    pub color: String,
+    /// Number of cubes drawn
+    pub count: u32,
+    /// Number of cubes of that color in the bag
+    pub available: u32,
+}
+
+impl Violation {
+    /// How many cubes are missing in the bag to make the draw possible.
+    pub fn excess(&self) -> u32 {
+        self.count - self.available
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    pub id: u32,
+    pub draws: Vec<Draw>,
+}
+
+impl Game {
+    /// A game is possible if every draw fits into the bag.
+    pub fn is_possible(&self, bag: &CubeSet) -> bool {
+        self.draws.iter().all(|draw| bag.contains(&draw.cubes))
+    }
+
+    /// The first draw which does not fit into the bag. Within a draw the colors are checked by name.
+    pub fn first_violation(&self, bag: &CubeSet) -> Option<Violation> {
+        self.draws
+            .iter()
+            .enumerate()
+            .find_map(|(draw_index, draw)| {
+                draw.cubes
+                    .iter()
+                    .find(|(color, count)| *count > bag.get(color))
+                    .map(|(color, count)| Violation {
+                        draw_index,
+                        color: color.to_string(),
+                        count,
+                        available: bag.get(color),
+                    })
+            })
+    }
+
+    /// The smallest bag which makes this game possible.
+    pub fn minimum_bag(&self) -> CubeSet {
+        self.draws
+            .iter()
+            .fold(CubeSet::new(), |acc, draw| acc.max(&draw.cubes))
+    }
+}
+
+impl Display for Draw {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.cubes)
+    }
+}
+
+/// Writes the game in canonical form, e.g. "Game 1: 3 blue, 4 red; 2 green"
+impl Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let draws: Vec<String> = self.draws.iter().map(|draw| draw.to_string()).collect();
+        write!(f, "Game {}: {}", self.id, draws.join("; "))
+    }
+}
+
+impl FromStr for Game {
+    type Err = ParseGameError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let captures = GAME_REGEX
+            .captures(line)
+            .ok_or_else(|| ParseGameError::InvalidFormat(line.to_string()))?;
+
+        let id_raw = captures.name("game_id").unwrap().as_str();
+        let id: u32 = id_raw
+            .parse()
+            .map_err(|_| ParseGameError::IdOutOfRange(id_raw.to_string()))?;
+        let draws: Vec<Draw> = captures
+            .name("draws")
+            .unwrap()
+            .as_str()
+            .split(';')
+            .map(draw_from_string)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Game { id, draws })
+    }
+}
+
+pub fn draw_from_string(input: &str) -> Result<Draw, ParseGameError> {
+    // each capture is a single color with its count, e.g. "3 blue"
+    // if a color is mentioned more than once, the max value is kept
+    let mut cubes = CubeSet::new();
+    for capt in MATCH_COLORS_REGEX.captures_iter(input) {
+        let color = capt.name("color").unwrap().as_str();
+        let count_raw = capt.name("count").unwrap().as_str();
+        let count: u32 = count_raw
+            .parse()
+            .map_err(|_| ParseGameError::CountOutOfRange(count_raw.to_string()))?;
+        cubes.set(color, count.max(cubes.get(color)));
+    }
+
+    Ok(Draw { cubes })
+}
+
+/// Parses all games of a log. Lines which are not a game are skipped,
+/// but a game with an id or count out of range fails with the offending line.
+pub fn parse_games(input: &str) -> Result<Vec<Game>, GameLogError> {
+    let mut games: Vec<Game> = vec![];
+
+    for (idx, line) in input.lines().enumerate() {
+        match line.parse::<Game>() {
+            Ok(game) => games.push(game),
+            Err(ParseGameError::InvalidFormat(_)) => continue,
+            Err(error) => {
+                return Err(GameLogError {
+                    line_number: idx + 1,
+                    line: line.to_string(),
+                    error,
+                })
+            }
+        }
+    }
+
+    Ok(games)
+}
+
+/// Sum of the ids of all games which are possible with the given bag.
+pub fn sum_of_possible_ids(games: &[Game], bag: &CubeSet) -> u64 {
+    games
+        .iter()
+        .filter(|game| game.is_possible(bag))
+        .map(|game| game.id as u64)
+        .sum()
+}
+
+/// All colors shown in any draw of the log, sorted by name.
+pub fn log_colors(games: &[Game]) -> Vec<String> {
+    let colors: BTreeSet<&str> = games
+        .iter()
+        .flat_map(|game| game.draws.iter())
+        .flat_map(|draw| draw.cubes.colors())
+        .collect();
+    colors.into_iter().map(|color| color.to_string()).collect()
+}
+
+/// Sum of the powers of the minimum bags of all games, `None` if it does not fit into an u128.
+/// The power of each game is taken over all colors of the log, see `CubeSet::power`.
+pub fn sum_of_powers(games: &[Game]) -> Option<u128> {
+    let colors = log_colors(games);
+    games.iter().try_fold(0u128, |acc, game| {
+        acc.checked_add(
+            game.minimum_bag()
+                .power(colors.iter().map(|c| c.as_str()))?,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_game() {
+        let game: Game = "Game 7: 3 blue, 4 yellow; 1 red, 2 purple, 6 blue; 2 yellow"
+            .parse()
+            .unwrap();
+
+        assert_eq!(game.id, 7);
+        assert_eq!(game.draws.len(), 3);
+        assert_eq!(game.draws[1].cubes.get("purple"), 2);
+        assert_eq!(game.draws[2].cubes.get("blue"), 0);
+
+        let minimum = game.minimum_bag();
+        assert_eq!(
+            minimum.iter().collect::<Vec<_>>(),
+            vec![("blue", 6), ("purple", 2), ("red", 1), ("yellow", 4)]
+        );
+        assert_eq!(minimum.power(minimum.colors()), Some(48));
+        assert!(game.is_possible(&minimum));
+        assert!(!game.is_possible(
+            &[("blue", 5), ("purple", 9), ("red", 9), ("yellow", 9)]
+                .into_iter()
+                .collect()
+        ));
+
+        assert!("Nope 1: 3 blue".parse::<Game>().is_err());
+    }
+
+    #[test]
+    fn test_parse_cube_set() {
+        let bag: CubeSet = " 12 red,13 green, 14 blue ".parse().unwrap();
+        assert_eq!(bag.get("green"), 13);
+
+        for input in [
+            "12 red, 13 grn, oops",
+            "12 red 13 green",
+            "red 12",
+            "12 red,",
+            "",
+        ] {
+            assert_eq!(
+                input.parse::<CubeSet>(),
+                Err(ParseGameError::InvalidFormat(input.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let game: Game = "Game 256: 300 red, 70000 blue".parse().unwrap();
+        assert_eq!(game.id, 256);
+        assert_eq!(game.minimum_bag().power(["blue", "red"]), Some(21_000_000));
+
+        let err = parse_games("Game 1: 1 red\nGame 2: 99999999999 red\n").unwrap_err();
+        assert_eq!(err.line_number, 2);
+        assert_eq!(
+            err.error,
+            ParseGameError::CountOutOfRange("99999999999".to_string())
+        );
+
+        let games =
+            parse_games(&"Game 4294967295: 4294967295 red, 4294967295 blue\n".repeat(3)).unwrap();
+        assert_eq!(
+            sum_of_powers(&games),
+            Some(3 * 4294967295u128 * 4294967295u128)
+        );
+        assert_eq!(
+            sum_of_possible_ids(&games, &games[0].minimum_bag()),
+            3 * 4294967295
+        );
+    }
+
+    #[test]
+    fn test_power_of_missing_color() {
+        // game 1 never shows green, so at least 0 green are needed and its power is 0
+        let games = parse_games("Game 1: 3 red, 4 blue\nGame 2: 2 red, 2 green, 2 blue").unwrap();
+        assert_eq!(log_colors(&games), vec!["blue", "green", "red"]);
+        assert_eq!(
+            games[0].minimum_bag().power(["blue", "green", "red"]),
+            Some(0)
+        );
+        assert_eq!(sum_of_powers(&games), Some(8));
+    }
+}
@@ -1,109 +1,208 @@
-use lazy_static::lazy_static;
-use regex::Regex;
-
-lazy_static! {
-    pub static ref MATCH_COLORS_REGEX: Regex = Regex::new(
-        r"((?P<blue>\d+)\sblue)(,\s)?|((?P<green>\d+)\sgreen)(,\s)?|((?P<red>\d+)\sred)(,\s)?"
-    )
-    .unwrap();
+use day_02::{
+    estimate::{estimate_all, estimate_game, BagEstimate},
+    explain::{explain, to_json},
+    log_colors, parse_games,
+    query::query_bags,
+    stats::{statistics, tightest_games},
+    sum_of_powers,
+    validate::{normalise, validate},
+    CubeSet,
+};
+
+// Usage: day_02 [--input <file>] [--bag "12 red, 13 green, 14 blue"]... [--estimate <max cubes per color>]
+//        day_02 [--input <file>] [--bag "12 red, 13 green, 14 blue"] --explain [--json]
+//        day_02 [--input <file>] [--bag "12 red, 13 green, 14 blue"] --stats
+//        day_02 [--input <file>] --validate
+//        day_02 [--input <file>] --normalise
+struct Args {
+    input_filename: String,
+    bags: Vec<CubeSet>,
+    estimate: Option<u32>,
+    validate: bool,
+    normalise: bool,
+    explain: bool,
+    json: bool,
+    stats: bool,
 }
 
-fn draw_from_string(input: &str) -> (u8, u8, u8) {
-    // for each capture (red, gree, blue) attempt to parse the value into an u8; otherwise assume 0
-    let captures: Vec<(u8, u8, u8)> = MATCH_COLORS_REGEX
-        .captures_iter(input)
-        .map(|capt| {
-            let red: u8 = capt.name("red").map_or(0, |c| c.as_str().parse().unwrap());
-            let green: u8 = capt
-                .name("green")
-                .map_or(0, |c| c.as_str().parse().unwrap());
-            let blue: u8 = capt.name("blue").map_or(0, |c| c.as_str().parse().unwrap());
-            (red, green, blue)
-        })
-        .collect();
+impl Args {
+    fn parse() -> Args {
+        let mut args = Args {
+            input_filename: "input2".to_string(),
+            bags: vec![],
+            estimate: None,
+            validate: false,
+            normalise: false,
+            explain: false,
+            json: false,
+            stats: false,
+        };
+
+        let mut raw = std::env::args().skip(1);
+        while let Some(arg) = raw.next() {
+            match arg.as_str() {
+                "--input" => args.input_filename = raw.next().expect("--input needs a file name"),
+                "--bag" => {
+                    let bag = raw
+                        .next()
+                        .expect("--bag needs a bag, e.g. \"12 red, 13 green\"");
+                    match bag.parse() {
+                        Ok(bag) => args.bags.push(bag),
+                        Err(e) => panic!("invalid bag '{bag}': {e}"),
+                    }
+                }
+                "--estimate" => {
+                    let max_count = raw
+                        .next()
+                        .expect("--estimate needs the max cubes per color");
+                    args.estimate = Some(max_count.parse().expect("invalid max cubes per color"));
+                }
+                "--validate" => args.validate = true,
+                "--normalise" => args.normalise = true,
+                "--explain" => args.explain = true,
+                "--json" => args.json = true,
+                "--stats" => args.stats = true,
+                _ => panic!("unknown argument '{arg}'"),
+            }
+        }
 
-    // The captures vector holds tuples for each color and looks like this: [(0,1,0), (2,0,0), (0,0,0)]
-    // Now just fold the vector by each max value yielding a single (R,G,B) tuple
-    let result = captures.iter().fold((0, 0, 0), |acc, tuple| {
-        (acc.0.max(tuple.0), acc.1.max(tuple.1), acc.2.max(tuple.2))
-    });
+        // Without any bags given, use the one of the puzzle
+        if args.bags.is_empty() {
+            args.bags.push(
+                [("red", 12), ("green", 13), ("blue", 14)]
+                    .into_iter()
+                    .collect(),
+            );
+        }
 
-    result
+        args
+    }
 }
 
-fn main() {
-    let input_filename = "input2";
-    let input = std::fs::read_to_string(input_filename).unwrap();
-
-    let re_split_game_id = Regex::new(r"Game\s(?P<game_id>\d+):\s(?P<draws>[\w\d\s,;]+)$").unwrap();
-
-    // Holds the final extracted state combining game_id with the respective draws: [(game_id), [(1,2,0), (0,0,1)]]
-    // The draws are always (red, green, blue).
-    let mut game_draws: Vec<(u8, Vec<(u8, u8, u8)>)> = Vec::new();
+fn print_estimate(name: &str, estimate: Option<BagEstimate>) {
+    let Some(estimate) = estimate else {
+        println!("{name}: no estimate within the search limit");
+        return;
+    };
 
-    for line in input.lines() {
-        let game = re_split_game_id.captures(line).map(|cap| {
-            let game_id: u8 = cap.name("game_id").unwrap().as_str().parse().unwrap();
-            let draws_raw: &str = cap.name("draws").unwrap().as_str();
-
-            let draws: Vec<(u8, u8, u8)> = draws_raw
-                .split(";")
-                .map(|draw| draw_from_string(draw))
-                .collect();
-
-            // println! {"{game_id} {:?}", draws};
+    let intervals: Vec<String> = estimate
+        .intervals
+        .iter()
+        .map(|(color, (low, high))| format!("{color} {low}..={high}"))
+        .collect();
+    println!(
+        "{name}: most likely bag is {} (log likelihood {:.3}), 95% intervals: {}{}",
+        estimate.bag,
+        estimate.log_likelihood,
+        intervals.join(", "),
+        if estimate.at_search_limit {
+            " (reaches the search limit)"
+        } else {
+            ""
+        }
+    );
+}
 
-            (game_id, draws)
-        });
+fn main() {
+    let args = Args::parse();
+    let input = std::fs::read_to_string(&args.input_filename).unwrap();
 
-        match game {
-            Some(game) => game_draws.push(game),
-            None => continue,
+    if args.validate {
+        let issues = validate(&input);
+        for issue in &issues {
+            println!("{issue}");
         }
+        println!("=> Found {} issues", issues.len());
+        return;
     }
 
-    // println! {"{:?}", game_draws};
-
-    let check_red = 12;
-    let check_green = 13;
-    let check_blue = 14;
-    let mut result_part1: u16 = 0;
+    if args.normalise {
+        match normalise(&input) {
+            Ok(normalised) => print!("{normalised}"),
+            Err(e) => panic!("cannot parse game log: {e}"),
+        }
+        return;
+    }
 
-    // Part 1: Filter the game state by invalid games and add the IDs of valid games
-    for (game_id, draws) in &game_draws {
-        let invalid_draws: Vec<(u8, u8, u8)> = draws
-            .iter()
-            .map(|draw| *draw)
-            .filter(|draw| draw.0 > check_red || draw.1 > check_green || draw.2 > check_blue)
-            .collect();
+    // Lines which are not a game are skipped
+    let games = match parse_games(&input) {
+        Ok(games) => games,
+        Err(e) => panic!("cannot parse game log: {e}"),
+    };
+
+    // Summary of the whole log, and the games which are closest to being impossible with the first bag
+    if args.stats {
+        println!("{}", statistics(&games));
+        println!("Tightest games for bag ({}):", args.bags[0]);
+        for (game_id, slack) in tightest_games(&games, &args.bags[0], 10) {
+            println!("  Game #{game_id}: {slack} cubes to spare");
+        }
+        return;
+    }
 
-        if invalid_draws.len() > 0 {
-            println! {"Game #{game_id} has invalid draws {:?}", draws};
+    // Explain parts 1 and 2 for every game, using the first bag
+    if args.explain {
+        let explanations = explain(&games, &args.bags[0]);
+        if args.json {
+            println!("{}", to_json(&explanations));
         } else {
-            println! {"Game #{game_id} is valid"};
-            result_part1 += *game_id as u16;
+            for explanation in &explanations {
+                println!("{explanation}");
+            }
+        }
+        return;
+    }
+
+    // Part 1: Check which games are possible with each of the bags and add the IDs of valid games
+    for report in query_bags(&games, &args.bags) {
+        println!(
+            "Bag ({}): {} of {} games are possible",
+            report.bag,
+            report.possible_ids().len(),
+            report.games.len()
+        );
+        for (game_id, violation) in report.impossible() {
+            println!(
+                "  Game #{game_id} is impossible: draw #{} has {} {}, that is {} more than the bag holds",
+                violation.draw_index + 1,
+                violation.count,
+                violation.color,
+                violation.excess()
+            );
         }
+        println! {"=> Result for part 1 is: {}", report.sum_of_possible_ids()};
     }
-    println! {"=> Result for part 1 is: {result_part1}"};
 
     // Part 2: Find the minimum cube count for each game
-    let mut result_part2: u32 = 0;
-    for (game_id, draws) in &game_draws {
-        let min_cube_count = draws.iter().fold((0, 0, 0), |acc, tuple| {
-            (acc.0.max(tuple.0), acc.1.max(tuple.1), acc.2.max(tuple.2))
-        });
+    // Colors a game never shows are needed 0 times, which makes its power 0
+    let colors = log_colors(&games);
+    for game in &games {
+        let min_cube_count = game.minimum_bag();
 
         println!(
-            "Game #{game_id} requires at least {} red, {} green and {} blue cubes to work",
-            min_cube_count.0, min_cube_count.1, min_cube_count.2
+            "Game #{} requires at least {} to work",
+            game.id, min_cube_count
         );
 
-        let power: u32 =
-            min_cube_count.0 as u32 * min_cube_count.1 as u32 * min_cube_count.2 as u32;
-        println! {"{:?}", power};
+        match min_cube_count.power(colors.iter().map(|color| color.as_str())) {
+            Some(power) => println! {"{:?}", power},
+            None => println! {"power does not fit into an u128"},
+        }
+    }
 
-        result_part2 += power;
+    match sum_of_powers(&games) {
+        Some(result_part2) => println!("=> Result for part 2 is: {result_part2}"),
+        None => println!("=> Result for part 2 does not fit into an u128"),
     }
 
-    println!("=> Result for part 2 is: {result_part2}");
+    // Estimate the most likely bag, assuming every draw is taken from the same bag without replacement
+    if let Some(max_count) = args.estimate {
+        for game in &games {
+            print_estimate(
+                &format!("Game #{}", game.id),
+                estimate_game(game, max_count),
+            );
+        }
+        print_estimate("All games", estimate_all(&games, max_count));
+    }
 }
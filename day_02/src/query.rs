@@ -0,0 +1,93 @@
+use crate::{CubeSet, Game, Violation};
+
+/// Whether a single game is possible with a bag, and if not, why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameFeasibility {
+    pub game_id: u32,
+    pub violation: Option<Violation>,
+}
+
+impl GameFeasibility {
+    pub fn is_possible(&self) -> bool {
+        self.violation.is_none()
+    }
+}
+
+/// The result of checking all games of a log against one candidate bag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BagReport {
+    pub bag: CubeSet,
+    pub games: Vec<GameFeasibility>,
+}
+
+impl BagReport {
+    pub fn new(games: &[Game], bag: &CubeSet) -> BagReport {
+        BagReport {
+            bag: bag.clone(),
+            games: games
+                .iter()
+                .map(|game| GameFeasibility {
+                    game_id: game.id,
+                    violation: game.first_violation(bag),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn possible_ids(&self) -> Vec<u32> {
+        self.games
+            .iter()
+            .filter(|game| game.is_possible())
+            .map(|game| game.game_id)
+            .collect()
+    }
+
+    pub fn impossible(&self) -> impl Iterator<Item = (u32, &Violation)> {
+        self.games
+            .iter()
+            .filter_map(|game| Some((game.game_id, game.violation.as_ref()?)))
+    }
+
+    pub fn sum_of_possible_ids(&self) -> u64 {
+        self.possible_ids().iter().map(|id| *id as u64).sum()
+    }
+}
+
+/// Checks the same game log against any number of candidate bags, one report per bag.
+pub fn query_bags(games: &[Game], bags: &[CubeSet]) -> Vec<BagReport> {
+    bags.iter().map(|bag| BagReport::new(games, bag)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_games;
+
+    #[test]
+    fn test_query_bags() {
+        let games = parse_games(
+            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green\n\
+             Game 2: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green",
+        )
+        .unwrap();
+        let bags: Vec<CubeSet> = ["12 red, 13 green, 14 blue", "20 red, 2 green, 6 blue"]
+            .iter()
+            .map(|bag| bag.parse().unwrap())
+            .collect();
+
+        let reports = query_bags(&games, &bags);
+        assert_eq!(reports[0].possible_ids(), vec![1]);
+        assert_eq!(reports[0].sum_of_possible_ids(), 1);
+
+        let (game_id, violation) = reports[0].impossible().next().unwrap();
+        assert_eq!(game_id, 2);
+        assert_eq!(violation.draw_index, 0);
+        assert_eq!(violation.color, "red");
+        assert_eq!(violation.excess(), 8);
+
+        let (game_id, violation) = reports[1].impossible().next().unwrap();
+        assert_eq!(game_id, 2);
+        assert_eq!(violation.color, "green");
+        assert_eq!(violation.excess(), 6);
+    }
+}
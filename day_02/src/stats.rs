@@ -0,0 +1,186 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+use crate::{CubeSet, Game};
+
+/// Summary of a whole game log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogStatistics {
+    pub games: usize,
+    pub draws: usize,
+    /// Number of draws of a game -> number of games with that many draws
+    pub draws_per_game: BTreeMap<usize, usize>,
+    /// Color -> count -> number of draws showing that many cubes of the color.
+    /// A draw without the color counts as 0 cubes.
+    pub histograms: BTreeMap<String, BTreeMap<u32, usize>>,
+    /// Pearson correlation of the counts of two colors over all draws, `None` if a color never varies
+    pub correlations: Vec<(String, String, Option<f64>)>,
+    /// The smallest bag which makes every game possible
+    pub smallest_bag: CubeSet,
+}
+
+/// The smallest bag which makes every game possible.
+pub fn smallest_bag(games: &[Game]) -> CubeSet {
+    games
+        .iter()
+        .fold(CubeSet::new(), |acc, game| acc.max(&game.minimum_bag()))
+}
+
+/// How many cubes the bag has to spare for the game, taking the color with the fewest spare cubes.
+/// A negative slack means the game is impossible with the bag.
+pub fn slack(game: &Game, bag: &CubeSet) -> i64 {
+    let minimum = game.minimum_bag();
+    minimum
+        .colors()
+        .chain(bag.colors())
+        .map(|color| bag.get(color) as i64 - minimum.get(color) as i64)
+        .min()
+        .unwrap_or(0)
+}
+
+/// The `limit` games with the least slack against the bag, tightest first.
+pub fn tightest_games(games: &[Game], bag: &CubeSet, limit: usize) -> Vec<(u32, i64)> {
+    let mut slacks: Vec<(u32, i64)> = games
+        .iter()
+        .map(|game| (game.id, slack(game, bag)))
+        .collect();
+    slacks.sort_by_key(|(id, slack)| (*slack, *id));
+    slacks.truncate(limit);
+    slacks
+}
+
+fn correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+
+    let covariance: f64 = xs
+        .iter()
+        .zip(ys)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance_x: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let variance_y: f64 = ys.iter().map(|y| (y - mean_y).powi(2)).sum();
+
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+pub fn statistics(games: &[Game]) -> LogStatistics {
+    let smallest_bag = smallest_bag(games);
+    let colors: Vec<&str> = smallest_bag.colors().collect();
+
+    let mut draws_per_game: BTreeMap<usize, usize> = BTreeMap::new();
+    for game in games {
+        *draws_per_game.entry(game.draws.len()).or_insert(0) += 1;
+    }
+
+    // counts[c][d] is the count of color c in draw d
+    let counts: Vec<Vec<u32>> = colors
+        .iter()
+        .map(|color| {
+            games
+                .iter()
+                .flat_map(|game| game.draws.iter())
+                .map(|draw| draw.cubes.get(color))
+                .collect()
+        })
+        .collect();
+
+    let mut histograms: BTreeMap<String, BTreeMap<u32, usize>> = BTreeMap::new();
+    for (color, color_counts) in colors.iter().zip(&counts) {
+        let histogram = histograms.entry(color.to_string()).or_default();
+        for count in color_counts {
+            *histogram.entry(*count).or_insert(0) += 1;
+        }
+    }
+
+    let as_floats: Vec<Vec<f64>> = counts
+        .iter()
+        .map(|color_counts| color_counts.iter().map(|count| *count as f64).collect())
+        .collect();
+    let mut correlations: Vec<(String, String, Option<f64>)> = vec![];
+    for a in 0..colors.len() {
+        for b in a + 1..colors.len() {
+            correlations.push((
+                colors[a].to_string(),
+                colors[b].to_string(),
+                correlation(&as_floats[a], &as_floats[b]),
+            ));
+        }
+    }
+
+    LogStatistics {
+        games: games.len(),
+        draws: games.iter().map(|game| game.draws.len()).sum(),
+        draws_per_game,
+        histograms,
+        correlations,
+        smallest_bag,
+    }
+}
+
+impl Display for LogStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} games with {} draws", self.games, self.draws)?;
+
+        writeln!(f, "Draws per game:")?;
+        for (draws, games) in &self.draws_per_game {
+            writeln!(f, "  {draws:>3} draws: {games} games")?;
+        }
+
+        for (color, histogram) in &self.histograms {
+            writeln!(f, "Histogram of {color}:")?;
+            for (count, draws) in histogram {
+                writeln!(f, "  {count:>3} cubes: {draws} draws")?;
+            }
+        }
+
+        writeln!(f, "Correlation between colors:")?;
+        for (a, b, correlation) in &self.correlations {
+            match correlation {
+                Some(value) => writeln!(f, "  {a} / {b}: {value:.3}")?,
+                None => writeln!(f, "  {a} / {b}: undefined")?,
+            }
+        }
+
+        write!(f, "Smallest bag for all games: {}", self.smallest_bag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_games;
+
+    #[test]
+    fn test_statistics() {
+        let games = parse_games(
+            "Game 1: 1 red, 2 blue; 2 red, 4 blue\n\
+             Game 2: 3 red, 6 blue\n\
+             Game 3: 5 red, 1 green; 1 blue",
+        )
+        .unwrap();
+
+        let stats = statistics(&games);
+        assert_eq!(stats.games, 3);
+        assert_eq!(stats.draws, 5);
+        assert_eq!(stats.draws_per_game, BTreeMap::from([(1, 1), (2, 2)]));
+        assert_eq!(stats.histograms["green"], BTreeMap::from([(0, 4), (1, 1)]));
+        assert_eq!(
+            stats.smallest_bag,
+            "5 red, 6 blue, 1 green".parse().unwrap()
+        );
+
+        let (a, b, correlation) = &stats.correlations[0];
+        assert_eq!((a.as_str(), b.as_str()), ("blue", "green"));
+        assert!(correlation.unwrap() < 0.0);
+
+        let bag: CubeSet = "5 red, 5 blue, 1 green".parse().unwrap();
+        assert_eq!(tightest_games(&games, &bag, 2), vec![(2, -1), (3, 0)]);
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+};
+
+use crate::{parse_games, Game, GameLogError, ParseGameError, GAME_REGEX, MATCH_COLORS_REGEX};
+
+/// Something which is wrong with a line of a game log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// The line is not a game and is skipped when parsing the log
+    SkippedLine,
+    /// The line looks like a game, but the id or a count cannot be parsed
+    InvalidValue(ParseGameError),
+    /// A draw without any cubes, e.g. "3 blue; ; 2 red"
+    EmptyDraw { draw_index: usize },
+    /// The same color occurs more than once within a draw, only the max count is kept
+    DuplicateColor { draw_index: usize, color: String },
+    /// The game id was already used in an earlier line
+    DuplicateId { id: u32, first_line_number: usize },
+    /// The game id does not follow the id of the previous game
+    NonSequentialId { id: u32, expected: u32 },
+}
+
+/// An `Issue` together with the line it was found in. `line_number` starts at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogIssue {
+    pub line_number: usize,
+    pub line: String,
+    pub issue: Issue,
+}
+
+impl Display for LogIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line_number)?;
+        match &self.issue {
+            Issue::SkippedLine => write!(f, "not a game, skipped: '{}'", self.line),
+            Issue::InvalidValue(e) => write!(f, "{e}"),
+            Issue::EmptyDraw { draw_index } => write!(f, "draw #{} is empty", draw_index + 1),
+            Issue::DuplicateColor { draw_index, color } => {
+                write!(
+                    f,
+                    "draw #{} mentions '{color}' more than once",
+                    draw_index + 1
+                )
+            }
+            Issue::DuplicateId {
+                id,
+                first_line_number,
+            } => write!(
+                f,
+                "game #{id} was already defined in line {first_line_number}"
+            ),
+            Issue::NonSequentialId { id, expected } => {
+                write!(f, "game #{id} should be game #{expected}")
+            }
+        }
+    }
+}
+
+/// Checks a hand-edited game log and reports every issue, in the order of the lines.
+pub fn validate(input: &str) -> Vec<LogIssue> {
+    let mut issues: Vec<LogIssue> = vec![];
+    let mut first_lines: HashMap<u32, usize> = HashMap::new();
+    let mut previous_id: Option<u32> = None;
+
+    for (idx, line) in input.lines().enumerate() {
+        let mut report = |issue: Issue| {
+            issues.push(LogIssue {
+                line_number: idx + 1,
+                line: line.to_string(),
+                issue,
+            })
+        };
+
+        let Some(captures) = GAME_REGEX.captures(line) else {
+            report(Issue::SkippedLine);
+            continue;
+        };
+        let game = match line.parse::<Game>() {
+            Ok(game) => game,
+            Err(e) => {
+                report(Issue::InvalidValue(e));
+                continue;
+            }
+        };
+
+        // The parsed draws already have duplicate colors folded, so check the raw draws instead
+        let draws_raw = captures.name("draws").unwrap().as_str();
+        for (draw_index, draw) in draws_raw.split(';').enumerate() {
+            let mut colors: HashSet<&str> = HashSet::new();
+            for capt in MATCH_COLORS_REGEX.captures_iter(draw) {
+                let color = capt.name("color").unwrap().as_str();
+                if !colors.insert(color) {
+                    report(Issue::DuplicateColor {
+                        draw_index,
+                        color: color.to_string(),
+                    });
+                }
+            }
+            if colors.is_empty() {
+                report(Issue::EmptyDraw { draw_index });
+            }
+        }
+
+        if let Some(first_line_number) = first_lines.get(&game.id) {
+            report(Issue::DuplicateId {
+                id: game.id,
+                first_line_number: *first_line_number,
+            });
+        } else {
+            let expected = previous_id.map_or(1, |id| id.saturating_add(1));
+            if game.id != expected {
+                report(Issue::NonSequentialId {
+                    id: game.id,
+                    expected,
+                });
+            }
+            first_lines.insert(game.id, idx + 1);
+        }
+        previous_id = Some(game.id);
+    }
+
+    issues
+}
+
+/// Re-emits all games of a log in canonical form, one game per line.
+///
+/// Colors are ordered by name and written as "<count> <color>", draws are separated by "; ".
+/// Lines which are not a game are dropped, duplicate colors within a draw are merged by their max.
+pub fn normalise(input: &str) -> Result<String, GameLogError> {
+    let games = parse_games(input)?;
+
+    Ok(games.iter().map(|game| format!("{game}\n")).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let input = "Game 1: 3 blue, 4 red\n\
+                     # comment\n\
+                     Game 3: 1 red, 2 red; ; 2 green\n\
+                     Game 3: 1 blue\n\
+                     Game 4: 99999999999 red";
+
+        let issues: Vec<(usize, Issue)> = validate(input)
+            .into_iter()
+            .map(|issue| (issue.line_number, issue.issue))
+            .collect();
+
+        assert_eq!(
+            issues,
+            vec![
+                (2, Issue::SkippedLine),
+                (
+                    3,
+                    Issue::DuplicateColor {
+                        draw_index: 0,
+                        color: "red".to_string()
+                    }
+                ),
+                (3, Issue::EmptyDraw { draw_index: 1 }),
+                (3, Issue::NonSequentialId { id: 3, expected: 2 }),
+                (
+                    4,
+                    Issue::DuplicateId {
+                        id: 3,
+                        first_line_number: 3
+                    }
+                ),
+                (
+                    5,
+                    Issue::InvalidValue(ParseGameError::CountOutOfRange("99999999999".to_string()))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalise() {
+        let input = "Game 1: 4 red,  3 blue;2 green, 1 green\nnope\nGame 2: 1 red";
+        assert_eq!(
+            normalise(input).unwrap(),
+            "Game 1: 3 blue, 4 red; 2 green\nGame 2: 1 red\n"
+        );
+    }
+}
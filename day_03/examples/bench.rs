@@ -0,0 +1,211 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use day_03::{Matrix, PartNumber};
+
+// Generates a square schematic with numbers, symbols and gears sprinkled in,
+// using a small LCG so every run benchmarks the same input.
+fn generate(size: usize) -> String {
+    let mut state: u64 = 42;
+    let mut next = || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (state >> 33) as usize
+    };
+
+    let mut input = String::with_capacity(size * (size + 1));
+    for _ in 0..size {
+        for _ in 0..size {
+            let c = match next() % 20 {
+                0..=5 => (b'0' + (next() % 10) as u8) as char,
+                6 => '*',
+                7 => "#$%&+-/=@".chars().nth(next() % 9).unwrap(),
+                _ => '.',
+            };
+            input.push(c);
+        }
+        input.push('\n');
+    }
+    input
+}
+
+// The schematic as it was stored before the flat byte buffer: one Vec<char> per row,
+// cloned by `rows`, with the same scans as before. Kept as the baseline of the benchmark.
+struct Legacy {
+    data: Vec<Vec<char>>,
+    rows: usize,
+    cols: usize,
+}
+
+impl Legacy {
+    fn new(input: &str) -> Legacy {
+        let rows = input.lines().count();
+        let cols = input.lines().next().unwrap().len();
+
+        let mut data: Vec<Vec<char>> = vec![vec!['\0'; cols]; rows];
+        for (row, line) in input.lines().enumerate() {
+            for (col, character) in line.chars().enumerate() {
+                data[row][col] = character
+            }
+        }
+
+        Legacy { data, rows, cols }
+    }
+
+    fn rows(&self) -> Vec<Vec<char>> {
+        self.data.clone()
+    }
+
+    fn get(&self, row: usize, col: usize) -> Option<char> {
+        self.data.get(row)?.get(col).copied()
+    }
+
+    fn is_symbol(c: char) -> bool {
+        !(c.is_numeric() || c == '.')
+    }
+
+    fn numbers(&self) -> Vec<PartNumber> {
+        let mut numbers: Vec<PartNumber> = vec![];
+        for (i, row) in self.data.iter().enumerate() {
+            let mut j = 0;
+            while j < row.len() {
+                if !row[j].is_ascii_digit() {
+                    j += 1;
+                    continue;
+                }
+                let start = j;
+                while j < row.len() && row[j].is_ascii_digit() {
+                    j += 1;
+                }
+                let text: String = row[start..j].iter().collect();
+                numbers.push(PartNumber {
+                    value: text.parse().unwrap(),
+                    row: i,
+                    start,
+                    end: j,
+                    text,
+                });
+            }
+        }
+        numbers
+    }
+
+    fn symbols(&self) -> Vec<(char, (usize, usize))> {
+        let mut symbols = vec![];
+        for (i, row) in self.rows().iter().enumerate() {
+            for (j, c) in row.iter().enumerate() {
+                if Legacy::is_symbol(*c) {
+                    symbols.push((*c, (i, j)));
+                }
+            }
+        }
+        symbols
+    }
+
+    fn surrounding_positions(&self, number: &PartNumber) -> Vec<(usize, usize)> {
+        let mut positions = vec![];
+        for i in number.row.saturating_sub(1)..=(number.row + 1).min(self.rows - 1) {
+            for j in number.start.saturating_sub(1)..=number.end.min(self.cols - 1) {
+                positions.push((i, j));
+            }
+        }
+        positions
+    }
+
+    fn part1(&self, numbers: &[PartNumber]) -> u128 {
+        numbers
+            .iter()
+            .filter(|number| {
+                self.surrounding_positions(number)
+                    .iter()
+                    .any(|(i, j)| Legacy::is_symbol(self.get(*i, *j).unwrap()))
+            })
+            .map(|number| number.value)
+            .sum()
+    }
+
+    fn part2(&self) -> u128 {
+        let mut parts: BTreeMap<(usize, usize), Vec<u128>> = self
+            .symbols()
+            .into_iter()
+            .filter(|(c, _)| *c == '*')
+            .map(|(_, pos)| (pos, vec![]))
+            .collect();
+        for number in self.numbers() {
+            for (i, j) in self.surrounding_positions(&number) {
+                if self.get(i, j) == Some('*') {
+                    parts.entry((i, j)).or_default().push(number.value);
+                }
+            }
+        }
+        parts
+            .values()
+            .filter(|parts| parts.len() == 2)
+            .filter_map(|parts| parts[0].checked_mul(parts[1]))
+            .sum()
+    }
+}
+
+// Runs `f` and returns its result with the time it took
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+// Usage: cargo run --release --example bench [size]
+fn main() {
+    let size: usize = std::env::args()
+        .nth(1)
+        .map_or(2000, |size| size.parse().expect("invalid size"));
+    let input = generate(size);
+
+    let (legacy, legacy_new) = time(|| Legacy::new(&input));
+    let (schematic, new) = time(|| Matrix::new(&input).unwrap());
+
+    let (legacy_numbers, legacy_numbers_time) = time(|| legacy.numbers());
+    let (numbers, numbers_time) = time(|| schematic.numbers());
+    assert_eq!(legacy_numbers.len(), numbers.len());
+
+    let (legacy_symbols, legacy_symbols_time) = time(|| legacy.symbols());
+    let (symbols, symbols_time) = time(|| schematic.symbols());
+    assert_eq!(legacy_symbols.len(), symbols.len());
+
+    let (legacy_part1, legacy_part1_time) = time(|| legacy.part1(&legacy_numbers));
+    let (part1, part1_time) = time(|| {
+        numbers
+            .iter()
+            .filter(|number| schematic.number_has_any_adjacent_symbol(number))
+            .map(|number| number.value)
+            .sum::<u128>()
+    });
+    assert_eq!(legacy_part1, part1);
+
+    let (legacy_part2, legacy_part2_time) = time(|| legacy.part2());
+    let (part2, part2_time) = time(|| {
+        schematic
+            .gears('*', 2)
+            .iter()
+            .filter_map(|gear| gear.ratio())
+            .sum::<u128>()
+    });
+    assert_eq!(legacy_part2, part2);
+
+    println!(
+        "{size}x{size} schematic, {} numbers, {} symbols",
+        numbers.len(),
+        symbols.len()
+    );
+    println!("         Vec<Vec<char>>     Matrix");
+    for (name, before, after) in [
+        ("new", legacy_new, new),
+        ("numbers", legacy_numbers_time, numbers_time),
+        ("symbols", legacy_symbols_time, symbols_time),
+        ("part 1", legacy_part1_time, part1_time),
+        ("part 2", legacy_part2_time, part2_time),
+    ] {
+        println!("{name:<8} {before:>14.2?} {after:>10.2?}");
+    }
+    println!("=> part 1 is {part1}, part 2 is {part2}");
+}
@@ -0,0 +1,141 @@
+use crate::{Matrix, PartNumber, Position};
+
+/// Inclusive bounds of a region of the schematic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub top: usize,
+    pub left: usize,
+    pub bottom: usize,
+    pub right: usize,
+}
+
+impl BoundingBox {
+    fn new(pos: Position) -> BoundingBox {
+        BoundingBox {
+            top: pos.row(),
+            left: pos.column(),
+            bottom: pos.row(),
+            right: pos.column(),
+        }
+    }
+
+    fn extend(&mut self, pos: Position) {
+        self.top = self.top.min(pos.row());
+        self.left = self.left.min(pos.column());
+        self.bottom = self.bottom.max(pos.row());
+        self.right = self.right.max(pos.column());
+    }
+}
+
+/// Part numbers and symbols which are connected through touching cells, e.g. an assembly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    pub parts: Vec<PartNumber>,
+    pub symbols: Vec<(char, Position)>,
+    pub bounding_box: BoundingBox,
+}
+
+impl Component {
+    pub fn sum(&self) -> u128 {
+        self.parts.iter().map(|part| part.value).sum()
+    }
+}
+
+impl Matrix {
+    fn is_occupied(&self, c: char) -> bool {
+        c.is_ascii_digit() || self.is_symbol(c)
+    }
+
+    /// Groups all numbers and symbols into connected components.
+    ///
+    /// Two cells are connected if both hold a digit or a symbol and they touch according to the adjacency.
+    /// Components are ordered by their first cell in row-major order.
+    pub fn components(&self) -> Vec<Component> {
+        // label[idx] is the component of the cell at row-major index idx
+        let mut label: Vec<Option<usize>> = vec![None; self.rows * self.cols];
+        let mut components: Vec<Component> = vec![];
+
+        for (pos, c) in self.cells() {
+            let idx = pos.row() * self.cols + pos.column();
+            if label[idx].is_some() || !self.is_occupied(c) {
+                continue;
+            }
+
+            // flood fill the new component, starting at this cell
+            let id = components.len();
+            let mut component = Component {
+                parts: vec![],
+                symbols: vec![],
+                bounding_box: BoundingBox::new(pos),
+            };
+            label[idx] = Some(id);
+            let mut stack = vec![pos];
+            while let Some(current) = stack.pop() {
+                let c = self.get(current.row(), current.column()).unwrap();
+                if self.is_symbol(c) {
+                    component.symbols.push((c, current));
+                }
+                component.bounding_box.extend(current);
+
+                for neighbour in self.neighbours(current) {
+                    let n_idx = neighbour.row() * self.cols + neighbour.column();
+                    let n_c = self.get(neighbour.row(), neighbour.column()).unwrap();
+                    if label[n_idx].is_none() && self.is_occupied(n_c) {
+                        label[n_idx] = Some(id);
+                        stack.push(neighbour);
+                    }
+                }
+            }
+            component.symbols.sort_by_key(|(_, pos)| *pos);
+            components.push(component);
+        }
+
+        for number in self.numbers() {
+            let idx = number.row * self.cols + number.start;
+            if let Some(id) = label[idx] {
+                components[id].parts.push(number);
+            }
+        }
+
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_components() {
+        let schematic = Matrix::new("467..114..\n...*......\n..35..633.\n......#...").unwrap();
+        let components = schematic.components();
+
+        let summary: Vec<(Vec<u128>, Vec<char>, u128)> = components
+            .iter()
+            .map(|component| {
+                (
+                    component.parts.iter().map(|part| part.value).collect(),
+                    component.symbols.iter().map(|(c, _)| *c).collect(),
+                    component.sum(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (vec![467, 35], vec!['*'], 502),
+                (vec![114], vec![], 114),
+                (vec![633], vec!['#'], 633),
+            ]
+        );
+        assert_eq!(
+            components[0].bounding_box,
+            BoundingBox {
+                top: 0,
+                left: 0,
+                bottom: 2,
+                right: 3
+            }
+        );
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{scan_digits, Gear, Matrix, PartNumber, Position, SchematicError};
+
+// A number is identified by its row and start column
+type NumberKey = (usize, usize);
+
+// A running sum which values are added to and removed from in any order.
+// The sum is kept exactly in two words, `None` values (ratios which do not fit into an u128) are counted.
+#[derive(Debug, Clone, Copy, Default)]
+struct Total {
+    low: u128,
+    high: u128,
+    overflowing: usize,
+}
+
+impl Total {
+    fn add(&mut self, value: Option<u128>) {
+        match value {
+            Some(value) => {
+                let (low, carry) = self.low.overflowing_add(value);
+                self.low = low;
+                self.high += carry as u128;
+            }
+            None => self.overflowing += 1,
+        }
+    }
+
+    fn sub(&mut self, value: Option<u128>) {
+        match value {
+            Some(value) => {
+                let (low, borrow) = self.low.overflowing_sub(value);
+                self.low = low;
+                self.high -= borrow as u128;
+            }
+            None => self.overflowing -= 1,
+        }
+    }
+
+    // The sum, `None` if it or one of its values does not fit into an u128
+    fn get(&self) -> Option<u128> {
+        (self.high == 0 && self.overflowing == 0).then_some(self.low)
+    }
+}
+
+/// An index of all part numbers and their adjacent symbols, kept up to date on every edit.
+///
+/// `set` only revisits the numbers around the changed cell, so the part sum and the sum of the
+/// gear ratios are available without rescanning the whole schematic.
+pub struct PartIndex {
+    matrix: Matrix,
+    /// numbers[row] holds the numbers of a row by their start column
+    numbers: Vec<BTreeMap<usize, PartNumber>>,
+    /// Every symbol with the numbers touching it
+    symbols: HashMap<Position, Vec<NumberKey>>,
+    gear_symbol: char,
+    arity: usize,
+    part_sum: Total,
+    gear_ratio_sum: Total,
+}
+
+impl PartIndex {
+    /// Indexes the schematic. Gears are `gear_symbol`s touching exactly `arity` numbers, e.g. '*' and 2.
+    pub fn new(matrix: Matrix, gear_symbol: char, arity: usize) -> PartIndex {
+        let mut index = PartIndex {
+            numbers: vec![BTreeMap::new(); matrix.rows],
+            symbols: HashMap::new(),
+            matrix,
+            gear_symbol,
+            arity,
+            part_sum: Total::default(),
+            gear_ratio_sum: Total::default(),
+        };
+
+        for (_, pos) in index.matrix.symbols() {
+            index.symbols.insert(pos, vec![]);
+        }
+        for number in index.matrix.numbers() {
+            index.attach(number);
+        }
+
+        index
+    }
+
+    pub fn matrix(&self) -> &Matrix {
+        &self.matrix
+    }
+
+    /// Sum of all numbers touching at least one symbol (part 1), `None` if it does not fit into an u128
+    pub fn part_sum(&self) -> Option<u128> {
+        self.part_sum.get()
+    }
+
+    /// Sum of the ratios of all gears (part 2), `None` if it or one of the ratios does not fit into an u128
+    pub fn gear_ratio_sum(&self) -> Option<u128> {
+        self.gear_ratio_sum.get()
+    }
+
+    /// All numbers in row-major order
+    pub fn numbers(&self) -> impl Iterator<Item = &PartNumber> {
+        self.numbers.iter().flat_map(|row| row.values())
+    }
+
+    /// All gears, ordered by position.
+    pub fn gears(&self) -> Vec<Gear> {
+        let mut gears: Vec<Gear> = self
+            .symbols
+            .keys()
+            .filter(|pos| self.is_gear(pos))
+            .map(|pos| {
+                let mut keys = self.symbols[pos].clone();
+                keys.sort();
+                Gear {
+                    symbol: self.gear_symbol,
+                    position: *pos,
+                    parts: keys
+                        .iter()
+                        .map(|(row, start)| self.numbers[*row][start].clone())
+                        .collect(),
+                }
+            })
+            .collect();
+        gears.sort_by_key(|gear| gear.position);
+        gears
+    }
+
+    /// Changes a single cell and updates the index.
+    /// Fails without changing anything if the cell would create a number which does not fit into an u128.
+    pub fn set(&mut self, row: usize, col: usize, data: char) -> Result<(), SchematicError> {
+        self.matrix.check_cell(row, col, data)?;
+
+        // Every number touching the cell may change its adjacency,
+        // and the numbers of the same row may grow, shrink, split or merge.
+        let pos = Position::new(row, col);
+        let mut keys: BTreeSet<NumberKey> = BTreeSet::new();
+        for other in self.matrix.touching(pos) {
+            if let Some((start, number)) = self.numbers[other.row()]
+                .range(..=other.column())
+                .next_back()
+            {
+                if number.end > other.column() {
+                    keys.insert((other.row(), *start));
+                }
+            }
+        }
+        keys.extend(
+            self.numbers[row]
+                .range(..=col + 1)
+                .rev()
+                .take_while(|(_, number)| number.end >= col)
+                .map(|(start, _)| (row, *start)),
+        );
+        let affected: Vec<PartNumber> = keys.into_iter().map(|key| self.detach(key)).collect();
+
+        if let Some(parts) = self.symbols.remove(&pos) {
+            debug_assert!(parts.is_empty());
+        }
+
+        self.matrix.set(row, col, data)?;
+        if self.matrix.is_symbol(data) {
+            self.symbols.insert(pos, vec![]);
+        }
+
+        // Numbers next to or on the cell are scanned again, all others keep their span
+        let mut low = col;
+        let mut high = col + 1;
+        for number in affected {
+            if number.row == row && number.start <= col + 1 && number.end >= col {
+                low = low.min(number.start);
+                high = high.max(number.end);
+            } else {
+                self.attach(number);
+            }
+        }
+        let cells = self.matrix.row(row).unwrap();
+        for number in scan_digits(row, cells, low..high)? {
+            self.attach(number);
+        }
+
+        Ok(())
+    }
+
+    fn is_gear(&self, pos: &Position) -> bool {
+        self.matrix.get(pos.row(), pos.column()) == Some(self.gear_symbol)
+            && self.symbols[pos].len() == self.arity
+    }
+
+    // Like `Gear::ratio`, 0 for symbols which are no gear
+    fn gear_ratio(&self, pos: &Position) -> Option<u128> {
+        if !self.is_gear(pos) {
+            return Some(0);
+        }
+        self.symbols[pos]
+            .iter()
+            .try_fold(1u128, |acc, (row, start)| {
+                acc.checked_mul(self.numbers[*row][start].value)
+            })
+    }
+
+    fn adjacent_symbols(&self, number: &PartNumber) -> Vec<Position> {
+        self.matrix
+            .calculate_surrounding_positions(number)
+            .into_iter()
+            .filter(|pos| self.symbols.contains_key(pos))
+            .collect()
+    }
+
+    fn attach(&mut self, number: PartNumber) {
+        let key = (number.row, number.start);
+        let adjacent = self.adjacent_symbols(&number);
+        if !adjacent.is_empty() {
+            self.part_sum.add(Some(number.value));
+        }
+        self.numbers[number.row].insert(number.start, number);
+
+        for pos in adjacent {
+            self.gear_ratio_sum.sub(self.gear_ratio(&pos));
+            self.symbols.get_mut(&pos).unwrap().push(key);
+            self.gear_ratio_sum.add(self.gear_ratio(&pos));
+        }
+    }
+
+    fn detach(&mut self, key: NumberKey) -> PartNumber {
+        let adjacent = self.adjacent_symbols(&self.numbers[key.0][&key.1]);
+        for pos in &adjacent {
+            self.gear_ratio_sum.sub(self.gear_ratio(pos));
+            self.symbols
+                .get_mut(pos)
+                .unwrap()
+                .retain(|other| *other != key);
+            self.gear_ratio_sum.add(self.gear_ratio(pos));
+        }
+
+        let number = self.numbers[key.0].remove(&key.1).unwrap();
+        if !adjacent.is_empty() {
+            self.part_sum.sub(Some(number.value));
+        }
+        number
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Adjacency;
+
+    fn full_scan(matrix: &Matrix) -> (Option<u128>, Option<u128>) {
+        let part_sum = matrix
+            .numbers()
+            .iter()
+            .filter(|number| matrix.number_has_any_adjacent_symbol(number))
+            .try_fold(0u128, |acc, number| acc.checked_add(number.value));
+        let gear_ratio_sum = matrix
+            .gears('*', 2)
+            .iter()
+            .try_fold(0u128, |acc, gear| acc.checked_add(gear.ratio()?));
+        (part_sum, gear_ratio_sum)
+    }
+
+    #[test]
+    fn test_incremental_updates() {
+        let input = "467..114..\n...*......\n..35..633.\n......#...\n617*......\n\
+                     .....+.58.\n..592.....\n......755.\n...$.*....\n.664.598..";
+        let index = PartIndex::new(Matrix::new(input).unwrap(), '*', 2);
+        assert_eq!(
+            (index.part_sum(), index.gear_ratio_sum()),
+            (Some(4361), Some(467835))
+        );
+
+        // random edits, after each one the index has to match a full scan
+        let adjacencies = vec![
+            Adjacency::default(),
+            Adjacency {
+                wrap: true,
+                ..Adjacency::orthogonal()
+            },
+            Adjacency {
+                offsets: vec![(0, 2), (1, 0), (-2, -1)],
+                wrap: true,
+            },
+        ];
+        for adjacency in adjacencies {
+            let matrix = Matrix::new(input).unwrap().with_adjacency(adjacency);
+            let mut index = PartIndex::new(matrix, '*', 2);
+            let mut state: u64 = 7;
+            for _ in 0..2000 {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let r = (state >> 33) as usize;
+                let c = ['.', '.', '.', '*', '#', '1', '2', '0', '9'][r % 9];
+                index.set((r / 9) % 10, (r / 90) % 10, c).unwrap();
+
+                assert_eq!(
+                    (index.part_sum(), index.gear_ratio_sum()),
+                    full_scan(index.matrix())
+                );
+                assert_eq!(
+                    index.numbers().cloned().collect::<Vec<_>>(),
+                    index.matrix().numbers()
+                );
+            }
+            assert_eq!(index.gears(), index.matrix().gears('*', 2));
+        }
+
+        // a digit joining two numbers into one which does not fit into an u128 changes nothing
+        let digits = "9".repeat(20);
+        let mut index = PartIndex::new(
+            Matrix::new(&format!("{digits}.{digits}\n*{}", ".".repeat(40))).unwrap(),
+            '*',
+            2,
+        );
+        assert!(index.set(0, 20, '9').is_err());
+        assert_eq!(index.numbers().count(), 2);
+        assert_eq!(index.part_sum(), digits.parse().ok());
+    }
+
+    #[test]
+    fn test_overflowing_sums() {
+        // the ratio of the gear does not fit into an u128, but both parts do
+        let digits = "9".repeat(20);
+        let matrix = Matrix::new(&format!("{digits}*{digits}")).unwrap();
+        assert_eq!(matrix.gears('*', 2)[0].ratio(), None);
+        let mut index = PartIndex::new(matrix, '*', 2);
+        let part: u128 = digits.parse().unwrap();
+        assert_eq!(index.part_sum(), Some(2 * part));
+        assert_eq!(index.gear_ratio_sum(), None);
+        assert_eq!(
+            (index.part_sum(), index.gear_ratio_sum()),
+            full_scan(index.matrix())
+        );
+
+        // without the gear the sum fits again
+        index.set(0, 20, '#').unwrap();
+        assert_eq!(index.gear_ratio_sum(), Some(0));
+        index.set(0, 20, '*').unwrap();
+        assert_eq!(index.gear_ratio_sum(), None);
+        // splits the left part into 9999999999 and 999999999, of which only the second touches the gear
+        index.set(0, 10, '.').unwrap();
+        assert_eq!(index.gear_ratio_sum(), Some(999_999_999 * part));
+
+        // the part sum overflows while every single part fits
+        let max = u128::MAX.to_string();
+        let mut index = PartIndex::new(Matrix::new(&format!("{max}#{max}")).unwrap(), '*', 2);
+        assert_eq!(index.part_sum(), None);
+        index.set(0, 39, '.').unwrap();
+        assert_eq!(index.part_sum(), Some(0));
+        index.set(0, 39, '#').unwrap();
+        assert_eq!(index.part_sum(), None);
+        // the right number no longer touches the symbol
+        index.set(0, 40, '.').unwrap();
+        assert_eq!(index.part_sum(), Some(u128::MAX));
+    }
+}
@@ -1,9 +1,22 @@
-use std::fmt::{self, Display};
-
-#[derive(Debug, Clone, Copy)]
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    fmt::{self, Display},
+    ops::Range,
+};
+
+pub mod components;
+pub mod index;
+pub mod render;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Position((usize, usize));
 
 impl Position {
+    pub fn new(row: usize, col: usize) -> Position {
+        Position((row, col))
+    }
+
     pub fn row(&self) -> usize {
         self.0 .0
     }
@@ -12,119 +25,529 @@ impl Position {
     }
 }
 
+/// A number in the schematic, spanning the columns `start..end` of a single row.
+/// `text` holds the digits as written, including leading zeros.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PartNumber {
+    pub value: u128,
+    pub row: usize,
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl PartNumber {
+    /// Position of the first digit
+    pub fn position(&self) -> Position {
+        Position((self.row, self.start))
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// A symbol in the schematic together with all part numbers touching it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gear {
+    pub symbol: char,
+    pub position: Position,
+    pub parts: Vec<PartNumber>,
+}
+
+impl Gear {
+    /// Product of all adjacent part numbers, `None` if it does not fit into an u128.
+    pub fn ratio(&self) -> Option<u128> {
+        self.parts
+            .iter()
+            .try_fold(1u128, |acc, part| acc.checked_mul(part.value))
+    }
+}
+
+/// Decides which cells of a schematic are symbols. Digits are never symbols.
+///
+/// The default matches the puzzle: '.' is empty and every other character is a symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolClassifier {
+    /// The character of an empty cell
+    pub empty: char,
+    /// Only these characters are symbols, `None` means every character which is not excluded otherwise
+    pub symbols: Option<HashSet<char>>,
+    pub letters_are_symbols: bool,
+    pub whitespace_are_symbols: bool,
+}
+
+impl Default for SymbolClassifier {
+    fn default() -> Self {
+        SymbolClassifier {
+            empty: '.',
+            symbols: None,
+            letters_are_symbols: true,
+            whitespace_are_symbols: true,
+        }
+    }
+}
+
+impl SymbolClassifier {
+    pub fn is_symbol(&self, c: char) -> bool {
+        if c.is_ascii_digit() || c == self.empty {
+            return false;
+        }
+        if c.is_alphabetic() && !self.letters_are_symbols {
+            return false;
+        }
+        if c.is_whitespace() && !self.whitespace_are_symbols {
+            return false;
+        }
+
+        match &self.symbols {
+            Some(symbols) => symbols.contains(&c),
+            None => true,
+        }
+    }
+}
+
+/// Decides which cells touch each other, given as (row, column) offsets from a cell.
+///
+/// The default matches the puzzle: the 8 surrounding cells, diagonals included, without wrap-around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adjacency {
+    /// Offsets of the touching cells, (0, 0) is ignored
+    pub offsets: Vec<(isize, isize)>,
+    /// Cells at an edge touch the cells at the opposite edge, like on a torus
+    pub wrap: bool,
+}
+
+impl Default for Adjacency {
+    fn default() -> Self {
+        Adjacency::radius(1)
+    }
+}
+
+impl Adjacency {
+    /// Only orthogonal contact: up, down, left and right
+    pub fn orthogonal() -> Adjacency {
+        Adjacency {
+            offsets: vec![(-1, 0), (0, -1), (0, 1), (1, 0)],
+            wrap: false,
+        }
+    }
+
+    /// All cells at most `radius` rows and columns away, diagonals included
+    pub fn radius(radius: usize) -> Adjacency {
+        let radius = radius as isize;
+        let mut offsets: Vec<(isize, isize)> = vec![];
+        for i in -radius..=radius {
+            for j in -radius..=radius {
+                if (i, j) != (0, 0) {
+                    offsets.push((i, j));
+                }
+            }
+        }
+        Adjacency {
+            offsets,
+            wrap: false,
+        }
+    }
+
+    /// The cells which `pos` touches in a grid of the given size, without `pos` itself.
+    pub fn neighbours(&self, pos: Position, rows: usize, cols: usize) -> Vec<Position> {
+        let mut neighbours: Vec<Position> = Vec::with_capacity(self.offsets.len());
+        self.push_neighbours(pos, rows, cols, &mut neighbours);
+        neighbours.sort();
+        neighbours.dedup();
+        neighbours
+    }
+
+    // Appends the cells which `pos` touches, unsorted and possibly more than once
+    fn push_neighbours(&self, pos: Position, rows: usize, cols: usize, out: &mut Vec<Position>) {
+        for (i, j) in &self.offsets {
+            let row = pos.row() as isize + i;
+            let col = pos.column() as isize + j;
+            let neighbour = if self.wrap {
+                Position::new(
+                    row.rem_euclid(rows as isize) as usize,
+                    col.rem_euclid(cols as isize) as usize,
+                )
+            } else if (0..rows as isize).contains(&row) && (0..cols as isize).contains(&col) {
+                Position::new(row as usize, col as usize)
+            } else {
+                continue;
+            };
+            if neighbour != pos {
+                out.push(neighbour);
+            }
+        }
+    }
+
+    // The same relation seen from the other side: the cells which touch `pos`
+    fn inverse(&self) -> Adjacency {
+        Adjacency {
+            offsets: self.offsets.iter().map(|(i, j)| (-i, -j)).collect(),
+            wrap: self.wrap,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchematicError {
+    /// The input has no rows, or the first row is empty
+    Empty,
+    /// Only ASCII cells are supported, `column` counts characters starting at 0
+    NonAscii {
+        line_number: usize,
+        column: usize,
+        character: char,
+    },
+    /// A row is not as wide as the first row
+    Ragged {
+        line_number: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// The empty cell of a classifier is not ASCII, so it would never match a cell
+    NonAsciiEmptyCell(char),
+    /// A number does not fit into an u128, `column` is its first digit starting at 0
+    NumberTooLarge {
+        line_number: usize,
+        column: usize,
+        digits: String,
+    },
+}
+
+impl Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchematicError::Empty => write!(f, "the schematic is empty"),
+            SchematicError::NonAscii {
+                line_number,
+                column,
+                character,
+            } => write!(
+                f,
+                "line {line_number}: '{character}' at column {column} is not ASCII"
+            ),
+            SchematicError::Ragged {
+                line_number,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line_number}: expected {expected} columns like the first line, found {found}"
+            ),
+            SchematicError::NonAsciiEmptyCell(c) => write!(f, "empty cell '{c}' is not ASCII"),
+            SchematicError::NumberTooLarge {
+                line_number,
+                column,
+                digits,
+            } => write!(
+                f,
+                "line {line_number}: number {digits} at column {column} does not fit into an u128"
+            ),
+        }
+    }
+}
+
+impl Error for SchematicError {}
+
+// The digits of u128::MAX
+const U128_MAX_DIGITS: &[u8] = b"340282366920938463463374607431768211455";
+
+// True if the digits parse into an u128, compared digit by digit instead of parsing them
+fn fits_into_u128(digits: &[u8]) -> bool {
+    let leading_zeros = digits.iter().take_while(|digit| **digit == b'0').count();
+    let digits = &digits[leading_zeros..];
+    digits.len() < U128_MAX_DIGITS.len()
+        || (digits.len() == U128_MAX_DIGITS.len() && digits <= U128_MAX_DIGITS)
+}
+
+// Fails on the first run of digits in the row which does not fit into an u128, without building the numbers
+fn check_digit_runs(row: usize, cells: &[u8]) -> Result<(), SchematicError> {
+    // only runs at least as long as u128::MAX may not fit, so most rows are done after counting
+    let mut run = 0;
+    let mut longest = 0;
+    for c in cells {
+        run = (run + 1) * c.is_ascii_digit() as usize;
+        longest = longest.max(run);
+    }
+    if longest < U128_MAX_DIGITS.len() {
+        return Ok(());
+    }
+
+    let mut start = 0;
+    for (j, c) in cells.iter().chain([&b'.']).enumerate() {
+        if c.is_ascii_digit() {
+            continue;
+        }
+        if !fits_into_u128(&cells[start..j]) {
+            return Err(SchematicError::NumberTooLarge {
+                line_number: row + 1,
+                column: start,
+                digits: String::from_utf8_lossy(&cells[start..j]).into_owned(),
+            });
+        }
+        start = j + 1;
+    }
+
+    Ok(())
+}
+
+/// Finds the numbers of a row which overlap the columns `range`, including their digits outside of it.
+/// Fails on the first number which does not fit into an u128.
+pub(crate) fn scan_digits(
+    row: usize,
+    cells: &[u8],
+    range: Range<usize>,
+) -> Result<Vec<PartNumber>, SchematicError> {
+    let (mut low, mut high) = (range.start, range.end.min(cells.len()));
+    while low > 0 && cells[low - 1].is_ascii_digit() {
+        low -= 1;
+    }
+    while high < cells.len() && cells[high].is_ascii_digit() {
+        high += 1;
+    }
+
+    let mut numbers: Vec<PartNumber> = vec![];
+    let mut j = low;
+    while j < high {
+        if !cells[j].is_ascii_digit() {
+            j += 1;
+            continue;
+        }
+
+        // consume the whole run of digits, which may end at the last column
+        let start = j;
+        while j < high && cells[j].is_ascii_digit() {
+            j += 1;
+        }
+
+        // digits are ASCII, so the slice is valid UTF-8
+        let text = std::str::from_utf8(&cells[start..j]).unwrap();
+        let value: u128 = text.parse().map_err(|_| SchematicError::NumberTooLarge {
+            line_number: row + 1,
+            column: start,
+            digits: text.to_string(),
+        })?;
+        numbers.push(PartNumber {
+            value,
+            row,
+            start,
+            end: j,
+            text: text.to_string(),
+        });
+    }
+
+    Ok(numbers)
+}
+
+/// The schematic, stored row by row in a single contiguous buffer.
+/// The cell at (row, col) lives at `row * cols + col`.
+#[derive(Debug)]
 pub struct Matrix {
-    data: Vec<Vec<char>>,
+    data: Vec<u8>,
     rows: usize,
     cols: usize,
+    classifier: SymbolClassifier,
+    adjacency: Adjacency,
+    // the adjacency is the default box, which is scanned without looking at the offsets
+    box_adjacency: bool,
 }
 
+/// Prints the schematic as plain text, see `render::Renderer` for highlighting.
 impl Display for Matrix {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for row in self.rows().iter().enumerate() {
-            write!(f, "{:?}\n", row)?;
+        for row in self.rows() {
+            writeln!(f, "{}", String::from_utf8_lossy(row))?;
         }
         write!(f, "")
     }
 }
 
 impl Matrix {
-    pub fn new(input: &str) -> Matrix {
-        let rows = input.lines().count();
-        let cols = input.lines().next().unwrap().len();
-
-        let mut data: Vec<Vec<char>> = vec![vec!['\0'; cols]; rows];
-        for (row, line) in input.lines().enumerate() {
-            for (col, character) in line.chars().enumerate() {
-                data[row][col] = character
+    /// Creates the matrix from ASCII input. Every row has to be as wide as the first one,
+    /// trailing empty lines are ignored.
+    pub fn new(input: &str) -> Result<Matrix, SchematicError> {
+        let lines: Vec<&str> = input.trim_end_matches(['\n', '\r']).lines().collect();
+        let cols = match lines.first() {
+            Some(line) if !line.is_empty() => line.len(),
+            _ => return Err(SchematicError::Empty),
+        };
+
+        let mut data: Vec<u8> = Vec::with_capacity(lines.len() * cols);
+        for (idx, line) in lines.iter().enumerate() {
+            if !line.is_ascii() {
+                let (column, character) = line
+                    .chars()
+                    .enumerate()
+                    .find(|(_, c)| !c.is_ascii())
+                    .unwrap();
+                return Err(SchematicError::NonAscii {
+                    line_number: idx + 1,
+                    column,
+                    character,
+                });
+            }
+            if line.len() != cols {
+                return Err(SchematicError::Ragged {
+                    line_number: idx + 1,
+                    expected: cols,
+                    found: line.len(),
+                });
             }
+            data.extend_from_slice(line.as_bytes());
         }
 
-        Matrix { data, rows, cols }
+        // every number has to fit into an u128, so `numbers` never fails later on
+        for (idx, row) in data.chunks_exact(cols).enumerate() {
+            check_digit_runs(idx, row)?;
+        }
+
+        Ok(Matrix {
+            data,
+            rows: lines.len(),
+            cols,
+            classifier: SymbolClassifier::default(),
+            adjacency: Adjacency::default(),
+            box_adjacency: true,
+        })
     }
 
-    pub fn rows(&self) -> Vec<Vec<char>> {
-        self.data.clone()
+    /// Replaces the default symbol classification, e.g. for schematics with ' ' as empty cell.
+    /// The empty cell has to be ASCII like all other cells.
+    pub fn with_classifier(
+        mut self,
+        classifier: SymbolClassifier,
+    ) -> Result<Matrix, SchematicError> {
+        if !classifier.empty.is_ascii() {
+            return Err(SchematicError::NonAsciiEmptyCell(classifier.empty));
+        }
+        self.classifier = classifier;
+        Ok(self)
     }
 
-    pub fn size(&self) -> (usize, usize) {
-        (self.rows, self.cols)
+    pub fn classifier(&self) -> &SymbolClassifier {
+        &self.classifier
     }
 
-    pub fn get(&self, row: usize, col: usize) -> Option<char> {
-        Some(self.data.get(row)?.get(col)?.clone())
+    /// Replaces the default 8-neighbour adjacency, e.g. with `Adjacency::orthogonal()`.
+    pub fn with_adjacency(mut self, adjacency: Adjacency) -> Matrix {
+        self.box_adjacency = adjacency == Adjacency::default();
+        self.adjacency = adjacency;
+        self
     }
 
-    pub fn set(&mut self, row: usize, col: usize, data: char) {
-        self.data[row][col] = data;
+    pub fn adjacency(&self) -> &Adjacency {
+        &self.adjacency
     }
 
-    pub fn numbers(&self) -> Vec<(u16, Position)> {
-        let mut numbers: Vec<(u16, Position)> = vec![];
+    /// The cells touching `pos`, according to the adjacency.
+    pub fn neighbours(&self, pos: Position) -> Vec<Position> {
+        self.adjacency.neighbours(pos, self.rows, self.cols)
+    }
 
-        let mut num_buffer = String::new();
+    // The cells which have `pos` as neighbour. Differs from `neighbours` for asymmetric offsets only.
+    fn touching(&self, pos: Position) -> Vec<Position> {
+        self.adjacency
+            .inverse()
+            .neighbours(pos, self.rows, self.cols)
+    }
 
-        for (i, row) in self.rows().iter().enumerate() {
-            for (j, c) in row.iter().enumerate() {
-                // 1. buffer empty, current char is numeric
-                // 2. buffer empty, current char is NOT numeric
-                if num_buffer.is_empty() {
-                    if c.is_numeric() {
-                        num_buffer += &c.to_string();
-                    }
-                    continue;
-                }
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.data.chunks_exact(self.cols.max(1))
+    }
 
-                // 3. buffer NOT empty, current char is numeric
-                // 4. buffer NOT empty, current char is NOT numeric
-                // 5. buffer NOT empty, but we're at the end of this row (last column)
-                if !num_buffer.is_empty() {
-                    if c.is_numeric() {
-                        num_buffer += &c.to_string();
-                    }
+    pub fn row(&self, row: usize) -> Option<&[u8]> {
+        if row >= self.rows {
+            return None;
+        }
+        Some(&self.data[row * self.cols..(row + 1) * self.cols])
+    }
 
-                    if !c.is_numeric() {
-                        let num_start = Position((i, j - num_buffer.len()));
-                        numbers.push((num_buffer.parse().unwrap(), num_start));
-                        num_buffer.clear();
-                        continue;
-                    }
+    /// The cells `cols` of a single row, without copying.
+    pub fn slice(&self, row: usize, cols: Range<usize>) -> Option<&[u8]> {
+        self.row(row)?.get(cols)
+    }
 
-                    if j == self.cols - 1 {
-                        let num_start = Position((i, j + 1 - num_buffer.len()));
-                        numbers.push((num_buffer.parse().unwrap(), num_start));
-                        num_buffer.clear();
-                        continue;
-                    }
-                }
-            }
-        }
+    /// All cells in row-major order
+    pub fn cells(&self) -> impl Iterator<Item = (Position, char)> + '_ {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(idx, c)| (Position((idx / self.cols, idx % self.cols)), *c as char))
+    }
 
-        numbers
+    pub fn size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
     }
 
-    pub fn symbols(&self) -> Vec<(char, Position)> {
-        let mut symbols: Vec<(char, Position)> = vec![];
+    pub fn get(&self, row: usize, col: usize) -> Option<char> {
+        if col >= self.cols {
+            return None;
+        }
+        self.row(row).map(|cells| cells[col] as char)
+    }
 
-        for (i, row) in self.rows().iter().enumerate() {
-            for (j, c) in row.iter().enumerate() {
-                if !Matrix::is_symbol(c) {
-                    continue;
-                }
+    /// Changes a single cell. Fails without changing anything if a digit would join
+    /// the numbers next to it into one which does not fit into an u128.
+    pub fn set(&mut self, row: usize, col: usize, data: char) -> Result<(), SchematicError> {
+        assert!(
+            row < self.rows && col < self.cols,
+            "({row},{col}) is out of bounds"
+        );
+        assert!(data.is_ascii(), "only ASCII cells are supported");
+        self.check_cell(row, col, data)?;
+        self.data[row * self.cols + col] = data as u8;
+        Ok(())
+    }
 
-                symbols.push((*c, Position((i, j))));
-            }
+    // Fails if writing `data` at (row, col) would create a number which does not fit into an u128
+    pub(crate) fn check_cell(
+        &self,
+        row: usize,
+        col: usize,
+        data: char,
+    ) -> Result<(), SchematicError> {
+        let Some(cells) = self.row(row) else {
+            return Ok(());
+        };
+        if col >= cells.len() || !data.is_ascii_digit() {
+            return Ok(());
         }
 
-        symbols
+        let mut cells = cells.to_vec();
+        cells[col] = data as u8;
+        scan_digits(row, &cells, col..col + 1).map(|_| ())
+    }
+
+    /// Finds all numbers of the schematic, scanning each row left to right.
+    ///
+    /// `new` and `set` reject numbers which do not fit into an u128, so every number has a value.
+    pub fn numbers(&self) -> Vec<PartNumber> {
+        self.rows()
+            .enumerate()
+            .flat_map(|(i, cells)| {
+                scan_digits(i, cells, 0..cells.len()).expect("numbers are checked on every write")
+            })
+            .collect()
+    }
+
+    pub fn symbols(&self) -> Vec<(char, Position)> {
+        self.cells()
+            .filter(|(_, c)| self.is_symbol(*c))
+            .map(|(pos, c)| (c, pos))
+            .collect()
     }
 
-    pub fn number_has_any_adjacent_symbol(&mut self, number: u16, pos: Position) -> bool {
-        let positions_to_check = self.calculate_surrounding_positions(number, pos);
+    pub fn number_has_any_adjacent_symbol(&self, number: &PartNumber) -> bool {
+        let positions_to_check = self.calculate_surrounding_positions(number);
 
         for check_pos in positions_to_check {
-            let c = self.get(check_pos.row(), check_pos.column()).unwrap();
-            if Matrix::is_symbol(&c) {
-                println! {"{number:>3} has adjacent symbol '{}' at ({},{})", c, check_pos.row(), check_pos.column()};
+            let c = self.data[check_pos.row() * self.cols + check_pos.column()] as char;
+            if self.is_symbol(c) {
                 return true;
             }
         }
@@ -132,68 +555,370 @@ impl Matrix {
         false
     }
 
-    pub fn number_has_special_adjacent_symbol(
-        &mut self,
-        symbol: char,
-        number: u16,
-        pos: Position,
-    ) -> Option<Position> {
-        let positions_to_check = self.calculate_surrounding_positions(number, pos);
+    /// All positions of `symbol` which touch the number. A number may touch more than one.
+    pub fn adjacent_symbols(&self, symbol: char, number: &PartNumber) -> Vec<Position> {
+        self.calculate_surrounding_positions(number)
+            .into_iter()
+            .filter(|pos| {
+                self.get(pos.row(), pos.column())
+                    .is_some_and(|c| c == symbol && self.is_symbol(c))
+            })
+            .collect()
+    }
 
-        for check_pos in positions_to_check {
-            let c = self.get(check_pos.row(), check_pos.column()).unwrap();
-            if c == symbol {
-                // println! {"{number:>3} has adjacent symbol '{}' at ({},{})", c, check_pos.row(), check_pos.column()};
-                return Some(check_pos);
+    /// Every position of `symbol` together with all part numbers touching it, ordered by position.
+    pub fn symbol_parts(&self, symbol: char) -> Vec<Gear> {
+        let mut parts: BTreeMap<Position, Vec<PartNumber>> = self
+            .symbols()
+            .into_iter()
+            .filter(|(c, _)| *c == symbol)
+            .map(|(_, pos)| (pos, vec![]))
+            .collect();
+
+        for number in self.numbers() {
+            for pos in self.adjacent_symbols(symbol, &number) {
+                parts.entry(pos).or_default().push(number.clone());
             }
         }
 
-        None
+        parts
+            .into_iter()
+            .map(|(position, parts)| Gear {
+                symbol,
+                position,
+                parts,
+            })
+            .collect()
     }
 
-    fn is_symbol(input: &char) -> bool {
-        !(input.is_numeric() || input.to_string().eq("."))
+    /// All positions of `symbol` which touch exactly `arity` part numbers, e.g. `gears('*', 2)` for part 2.
+    pub fn gears(&self, symbol: char, arity: usize) -> Vec<Gear> {
+        self.symbol_parts(symbol)
+            .into_iter()
+            .filter(|gear| gear.parts.len() == arity)
+            .collect()
     }
 
-    fn calculate_surrounding_positions(&mut self, number: u16, pos: Position) -> Vec<Position> {
-        // Given the number '123' all positions with '.' need to be checked
-        // The Position points to '1'
+    pub fn is_symbol(&self, input: char) -> bool {
+        self.classifier.is_symbol(input)
+    }
+
+    fn calculate_surrounding_positions(&self, number: &PartNumber) -> Vec<Position> {
+        // Given the number '123' all positions with '.' need to be checked,
+        // with the default adjacency that is the box around the number
         // . . . . .
         // . 1 2 3 .
         // . . . . .
-        let mut positions_to_check: Vec<Position> = vec![];
-
-        // calculate left border column
-        let mut left_col = pos.column();
-        if left_col > 0 {
-            left_col = pos.column() - 1;
+        if self.box_adjacency {
+            let left_col = number.start.saturating_sub(1);
+            let right_col = number.end.min(self.cols - 1);
+            let upper_row = number.row.saturating_sub(1);
+            let lower_row = (number.row + 1).min(self.rows - 1);
+
+            let mut positions_to_check: Vec<Position> =
+                Vec::with_capacity(2 * (number.len() + 2) + 2);
+            for i in upper_row..=lower_row {
+                for j in left_col..=right_col {
+                    // the digits of the number itself are never symbols
+                    if i != number.row || !(number.start..number.end).contains(&j) {
+                        positions_to_check.push(Position((i, j)));
+                    }
+                }
+            }
+            return positions_to_check;
         }
 
-        // calculate right border column
-        let mut right_col = pos.column() + number.to_string().len() - 1;
-        if right_col < self.cols - 1 {
-            right_col += 1;
+        let mut positions_to_check: Vec<Position> =
+            Vec::with_capacity(number.len() * self.adjacency.offsets.len());
+        for col in number.start..number.end {
+            self.adjacency.push_neighbours(
+                Position::new(number.row, col),
+                self.rows,
+                self.cols,
+                &mut positions_to_check,
+            );
         }
+        positions_to_check.retain(|pos| {
+            pos.row() != number.row || !(number.start..number.end).contains(&pos.column())
+        });
+        positions_to_check.sort();
+        positions_to_check.dedup();
+        positions_to_check
+    }
+}
 
-        // calculate upper row border
-        let mut upper_row = pos.row();
-        if upper_row > 0 {
-            upper_row -= 1;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers() {
+        let input = format!(
+            "{:.<30}\n{:.<28}42\n{}",
+            "007.*12", "...#.99", "123456789012345678901234567890"
+        );
+        let schematic = Matrix::new(&input).unwrap();
+        let numbers = schematic.numbers();
+
+        assert_eq!(numbers.len(), 5);
+        assert_eq!(numbers[0].text, "007");
+        assert_eq!(numbers[0].value, 7);
+        assert_eq!((numbers[0].start, numbers[0].end), (0, 3));
+        assert_eq!(
+            (numbers[2].row, numbers[2].start, numbers[2].end),
+            (1, 5, 7)
+        );
+        // numbers touching the right edge are found as well
+        assert_eq!((numbers[3].value, numbers[3].end), (42, 30));
+        assert_eq!(numbers[4].value, 123456789012345678901234567890);
+
+        // the leading zeros count for the span, '#' touches the last '7'
+        assert!(schematic.number_has_any_adjacent_symbol(&numbers[0]));
+        assert!(schematic.number_has_any_adjacent_symbol(&numbers[1]));
+        assert!(schematic.number_has_any_adjacent_symbol(&numbers[2]));
+        assert!(!schematic.number_has_any_adjacent_symbol(&numbers[3]));
+        assert!(schematic.number_has_any_adjacent_symbol(&numbers[4]));
+    }
 
-        // calculate lower row border
-        let mut lower_row = pos.row();
-        if lower_row < self.rows - 1 {
-            lower_row += 1;
+    #[test]
+    fn test_flat_buffer() {
+        let mut schematic = Matrix::new("12.\n.*.\n..3").unwrap();
+        assert_eq!(schematic.size(), (3, 3));
+        assert_eq!(schematic.row(1), Some(&b".*."[..]));
+        assert_eq!(schematic.row(3), None);
+        assert_eq!(schematic.slice(2, 1..3), Some(&b".3"[..]));
+        // a column past the end must not wrap into the next row
+        assert_eq!(schematic.get(0, 3), None);
+        assert_eq!(schematic.get(2, 2), Some('3'));
+
+        schematic.set(0, 2, '#').unwrap();
+        assert_eq!(schematic.rows().next(), Some(&b"12#"[..]));
+        assert_eq!(schematic.cells().nth(2), Some((Position::new(0, 2), '#')));
+        assert_eq!(schematic.symbols().len(), 2);
+    }
+
+    #[test]
+    fn test_classifier() {
+        let input = "12 A 3\n  #   \n4 * B5";
+        assert_eq!(Matrix::new(input).unwrap().symbols().len(), 13);
+
+        let schematic = Matrix::new(input)
+            .unwrap()
+            .with_classifier(SymbolClassifier {
+                empty: ' ',
+                letters_are_symbols: false,
+                ..Default::default()
+            })
+            .unwrap();
+        let symbols: Vec<char> = schematic.symbols().iter().map(|(c, _)| *c).collect();
+        assert_eq!(symbols, vec!['#', '*']);
+
+        let schematic = Matrix::new(input)
+            .unwrap()
+            .with_classifier(SymbolClassifier {
+                empty: ' ',
+                symbols: Some(HashSet::from(['*'])),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(schematic.symbols().len(), 1);
+        assert!(!schematic.is_symbol('#'));
+
+        let err = Matrix::new(input)
+            .unwrap()
+            .with_classifier(SymbolClassifier {
+                empty: '·',
+                ..Default::default()
+            });
+        assert_eq!(err.unwrap_err(), SchematicError::NonAsciiEmptyCell('·'));
+
+        // a '*' which is not a symbol is no gear either, for both gear APIs
+        for (symbols, gears) in [(HashSet::from(['#']), 0), (HashSet::from(['*']), 1)] {
+            let schematic = Matrix::new("12*34\n..#..")
+                .unwrap()
+                .with_classifier(SymbolClassifier {
+                    symbols: Some(symbols),
+                    ..Default::default()
+                })
+                .unwrap();
+            let number = &schematic.numbers()[0];
+            assert_eq!(schematic.adjacent_symbols('*', number).len(), gears);
+            assert_eq!(schematic.gears('*', 2).len(), gears);
+            let kind = render::Renderer::new(&schematic, '*', 2).kind(0, 2);
+            assert_eq!(kind == render::CellKind::Gear, gears == 1);
+
+            let expected = schematic.gears('*', 2);
+            assert_eq!(index::PartIndex::new(schematic, '*', 2).gears(), expected);
         }
+    }
 
-        // calculate all positions
-        for i in upper_row..=lower_row {
-            for j in left_col..=right_col {
-                positions_to_check.push(Position((i, j)))
+    #[test]
+    fn test_invalid_input() {
+        assert_eq!(
+            Matrix::new("1·*\n...").unwrap_err(),
+            SchematicError::NonAscii {
+                line_number: 1,
+                column: 1,
+                character: '·'
             }
+        );
+        // a short line must not be padded with cells which count as symbols
+        assert_eq!(
+            Matrix::new("....\n..12\n.5\n").unwrap_err(),
+            SchematicError::Ragged {
+                line_number: 3,
+                expected: 4,
+                found: 2
+            }
+        );
+        assert!(Matrix::new("12\n123").is_err());
+        assert_eq!(Matrix::new("").unwrap_err(), SchematicError::Empty);
+        assert_eq!(Matrix::new("12.\n.*.\n\n").unwrap().size(), (2, 3));
+    }
+
+    #[test]
+    fn test_number_too_large() {
+        let max = u128::MAX.to_string();
+        let schematic = Matrix::new(&format!(".{max}*")).unwrap();
+        assert_eq!(schematic.numbers()[0].value, u128::MAX);
+
+        // the digit check agrees with parsing, leading zeros included
+        let zeros = "0".repeat(45);
+        for digits in [
+            max.clone(),
+            "340282366920938463463374607431768211456".to_string(),
+            "999999999999999999999999999999999999999".to_string(),
+            format!("{zeros}{max}"),
+            format!("{zeros}1{max}"),
+            zeros.clone(),
+        ] {
+            assert_eq!(
+                fits_into_u128(digits.as_bytes()),
+                digits.parse::<u128>().is_ok(),
+                "{digits}"
+            );
+            assert_eq!(Matrix::new(&digits).is_ok(), digits.parse::<u128>().is_ok());
         }
 
-        positions_to_check
+        let digits = "1".repeat(40);
+        assert_eq!(
+            Matrix::new(&format!("..\n.{digits}*")).unwrap_err(),
+            SchematicError::Ragged {
+                line_number: 2,
+                expected: 2,
+                found: 42
+            }
+        );
+        assert_eq!(
+            Matrix::new(&format!("*{digits}.")).unwrap_err(),
+            SchematicError::NumberTooLarge {
+                line_number: 1,
+                column: 1,
+                digits: digits.clone()
+            }
+        );
+
+        // joining two numbers must not overflow, and leaves the cell as it was
+        let mut schematic = Matrix::new(&format!("{}.{}", &digits[..20], &digits[..19])).unwrap();
+        assert!(matches!(
+            schematic.set(0, 20, '1'),
+            Err(SchematicError::NumberTooLarge { column: 0, .. })
+        ));
+        assert_eq!(schematic.get(0, 20), Some('.'));
+        assert_eq!(schematic.numbers().len(), 2);
+        schematic.set(0, 20, '*').unwrap();
+    }
+
+    #[test]
+    fn test_gears() {
+        // (1,21) and (12,1) collided with the old string keys, both were "121", and 5 touches two gears
+        let mut rows = vec![".".repeat(22); 14];
+        rows[0] = format!("{:.<22}", "..................3");
+        rows[1] = format!("{:.<22}", "...................*5*");
+        rows[2] = format!("{:.<22}", ".....................7");
+        rows[11] = format!("{:.<22}", "2");
+        rows[12] = format!("{:.<22}", ".*");
+        rows[13] = format!("{:.<22}", "..9");
+        let schematic = Matrix::new(&rows.join("\n")).unwrap();
+
+        let gears = schematic.gears('*', 2);
+        let summary: Vec<(Position, Vec<u128>, Option<u128>)> = gears
+            .iter()
+            .map(|gear| {
+                let values = gear.parts.iter().map(|part| part.value).collect();
+                (gear.position, values, gear.ratio())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Position::new(1, 19), vec![3, 5], Some(15)),
+                (Position::new(1, 21), vec![5, 7], Some(35)),
+                (Position::new(12, 1), vec![2, 9], Some(18)),
+            ]
+        );
+
+        assert_eq!(schematic.symbol_parts('*').len(), 3);
+        assert!(schematic.gears('*', 3).is_empty());
+    }
+
+    #[test]
+    fn test_adjacency() {
+        // 1 touches '#' diagonally only, 2 touches '*' across the right edge
+        let input = "1...\n.#..\n*..2";
+        let schematic = Matrix::new(input).unwrap();
+        let parts: Vec<u128> = schematic
+            .numbers()
+            .iter()
+            .filter(|number| schematic.number_has_any_adjacent_symbol(number))
+            .map(|number| number.value)
+            .collect();
+        assert_eq!(parts, vec![1]);
+
+        let schematic = Matrix::new(input).unwrap().with_adjacency(Adjacency {
+            wrap: true,
+            ..Adjacency::orthogonal()
+        });
+        let parts: Vec<u128> = schematic
+            .numbers()
+            .iter()
+            .filter(|number| schematic.number_has_any_adjacent_symbol(number))
+            .map(|number| number.value)
+            .collect();
+        assert_eq!(parts, vec![1, 2]);
+        assert_eq!(
+            schematic.neighbours(Position::new(0, 0)),
+            vec![
+                Position::new(0, 1),
+                Position::new(0, 3),
+                Position::new(1, 0),
+                Position::new(2, 0)
+            ]
+        );
+
+        assert_eq!(Adjacency::radius(2).offsets.len(), 24);
+        assert_eq!(
+            Matrix::new(input)
+                .unwrap()
+                .neighbours(Position::new(1, 1))
+                .len(),
+            8
+        );
+
+        // the box of the default adjacency matches the same offsets in another order
+        let input = "12.3\n..45\n6...\n.789";
+        let default = Matrix::new(input).unwrap();
+        let mut offsets = Adjacency::default().offsets;
+        offsets.reverse();
+        let reversed = Matrix::new(input).unwrap().with_adjacency(Adjacency {
+            offsets,
+            wrap: false,
+        });
+        for number in default.numbers() {
+            assert_eq!(
+                default.calculate_surrounding_positions(&number),
+                reversed.calculate_surrounding_positions(&number)
+            );
+        }
     }
 }
@@ -1,45 +1,69 @@
-use std::collections::HashMap;
-
-use day_03::Matrix;
+use day_03::{render::Renderer, Adjacency, Matrix};
 
 fn main() {
     let input_filename = "input2";
     let input = std::fs::read_to_string(input_filename).unwrap();
 
-    let mut schematic = Matrix::new(&input);
+    // '--orthogonal' counts only up, down, left and right as touching, '--wrap' connects opposite edges
+    let args: Vec<String> = std::env::args().collect();
+    let mut adjacency = if args.iter().any(|arg| arg == "--orthogonal") {
+        Adjacency::orthogonal()
+    } else {
+        Adjacency::default()
+    };
+    adjacency.wrap = args.iter().any(|arg| arg == "--wrap");
+
+    let schematic = match Matrix::new(&input) {
+        Ok(schematic) => schematic.with_adjacency(adjacency),
+        Err(e) => panic!("cannot read schematic: {e}"),
+    };
 
-    println!("{schematic}");
+    // '--ansi' highlights parts, orphan numbers, symbols and gears, '--html <file>' writes them to a page
+    if args.iter().any(|arg| arg == "--ansi") {
+        print!("{}", Renderer::new(&schematic, '*', 2).ansi());
+    } else {
+        println!("{schematic}");
+    }
+    if let Some(pos) = args.iter().position(|arg| arg == "--html") {
+        let html_filename = args.get(pos + 1).expect("--html needs a file name");
+        std::fs::write(html_filename, Renderer::new(&schematic, '*', 2).html()).unwrap();
+        println!("=> Wrote {html_filename}");
+    }
 
     // part 1
-    let mut result_part1: u32 = 0;
+    let mut result_part1: u128 = 0;
     for number in schematic.numbers() {
-        if !schematic.number_has_any_adjacent_symbol(number.0, number.1) {
+        if !schematic.number_has_any_adjacent_symbol(&number) {
             continue;
         }
-        result_part1 += number.0 as u32;
+        result_part1 += number.value;
     }
     println!("\n=> Result for part 1 is {result_part1}");
 
     // part 2
-    let mut gear_positions: HashMap<String, Vec<u16>> = HashMap::new();
-    for number in schematic.numbers() {
-        if let Some(pos) = schematic.number_has_special_adjacent_symbol('*', number.0, number.1) {
-            let pos_key = format!("{}{}", pos.row(), pos.column());
-
-            if let Some(existing) = gear_positions.get_mut(&pos_key) {
-                existing.push(number.0);
-            } else {
-                gear_positions.insert(pos_key, vec![number.0]);
-            }
-        }
-    }
-
-    let mut result_part2: u32 = 0;
-    for (_, number) in gear_positions {
-        if number.len() == 2 {
-            result_part2 += number[0] as u32 * number[1] as u32;
-        }
+    let mut result_part2: u128 = 0;
+    for gear in schematic.gears('*', 2) {
+        result_part2 += gear.ratio().expect("gear ratio does not fit into an u128");
     }
 
     println!("=> Result for part 2 is {result_part2}");
+
+    // assemblies: numbers and symbols which are connected through touching cells
+    let components = schematic.components();
+    if let Some(largest) = components
+        .iter()
+        .max_by_key(|component| component.parts.len())
+    {
+        let bbox = largest.bounding_box;
+        println!(
+            "=> Found {} components, the largest has {} parts summing to {} within ({},{})..=({},{})",
+            components.len(),
+            largest.parts.len(),
+            largest.sum(),
+            bbox.top,
+            bbox.left,
+            bbox.bottom,
+            bbox.right
+        );
+    }
 }
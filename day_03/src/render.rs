@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+
+use crate::Matrix;
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// What a single cell is highlighted as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellKind {
+    Empty,
+    /// Digit of a number touching a symbol (part 1)
+    Part,
+    /// Digit of a number without any adjacent symbol
+    Orphan,
+    Symbol,
+    /// A gear symbol touching exactly `arity` numbers (part 2)
+    Gear,
+}
+
+impl CellKind {
+    fn ansi(&self) -> Option<&'static str> {
+        match self {
+            CellKind::Empty => None,
+            CellKind::Part => Some("\x1b[32m"),
+            CellKind::Orphan => Some("\x1b[31m"),
+            CellKind::Symbol => Some("\x1b[33m"),
+            CellKind::Gear => Some("\x1b[1;35m"),
+        }
+    }
+
+    fn css_class(&self) -> Option<&'static str> {
+        match self {
+            CellKind::Empty => None,
+            CellKind::Part => Some("part"),
+            CellKind::Orphan => Some("orphan"),
+            CellKind::Symbol => Some("symbol"),
+            CellKind::Gear => Some("gear"),
+        }
+    }
+}
+
+/// Renders a schematic with its parts, orphan numbers, symbols and gears highlighted.
+pub struct Renderer<'a> {
+    matrix: &'a Matrix,
+    kinds: Vec<CellKind>,
+    /// ratios[row] are the gears of a row with their ratios, in column order
+    ratios: BTreeMap<usize, Vec<(usize, u128)>>,
+}
+
+impl<'a> Renderer<'a> {
+    /// Classifies every cell. Gears are `gear_symbol`s touching exactly `arity` numbers, e.g. '*' and 2.
+    pub fn new(matrix: &'a Matrix, gear_symbol: char, arity: usize) -> Renderer<'a> {
+        let (rows, cols) = matrix.size();
+        let mut kinds = vec![CellKind::Empty; rows * cols];
+
+        for (pos, c) in matrix.cells() {
+            if matrix.is_symbol(c) {
+                kinds[pos.row() * cols + pos.column()] = CellKind::Symbol;
+            }
+        }
+
+        for number in matrix.numbers() {
+            let kind = if matrix.number_has_any_adjacent_symbol(&number) {
+                CellKind::Part
+            } else {
+                CellKind::Orphan
+            };
+            for col in number.start..number.end {
+                kinds[number.row * cols + col] = kind;
+            }
+        }
+
+        // the gears are ordered by position, so every row is in column order
+        let mut ratios: BTreeMap<usize, Vec<(usize, u128)>> = BTreeMap::new();
+        for gear in matrix.gears(gear_symbol, arity) {
+            kinds[gear.position.row() * cols + gear.position.column()] = CellKind::Gear;
+            if let Some(ratio) = gear.ratio() {
+                ratios
+                    .entry(gear.position.row())
+                    .or_default()
+                    .push((gear.position.column(), ratio));
+            }
+        }
+
+        Renderer {
+            matrix,
+            kinds,
+            ratios,
+        }
+    }
+
+    pub fn kind(&self, row: usize, col: usize) -> CellKind {
+        self.kinds[row * self.matrix.size().1 + col]
+    }
+
+    // The gears of a row with their ratios, in column order
+    fn gear_ratios(&self, row: usize) -> &[(usize, u128)] {
+        self.ratios
+            .get(&row)
+            .map_or(&[], |ratios| ratios.as_slice())
+    }
+
+    /// The schematic with ANSI colors for the terminal. The ratios of the gears are listed after each row.
+    pub fn ansi(&self) -> String {
+        let mut output = String::new();
+
+        for (i, row) in self.matrix.rows().enumerate() {
+            for (j, c) in row.iter().enumerate() {
+                match self.kind(i, j).ansi() {
+                    Some(color) => {
+                        output.push_str(color);
+                        output.push(*c as char);
+                        output.push_str(ANSI_RESET);
+                    }
+                    None => output.push(*c as char),
+                }
+            }
+
+            for (col, ratio) in self.gear_ratios(i) {
+                output.push_str(&format!("  gear at {col}: {ratio}"));
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// A standalone HTML document. Hovering a gear shows its ratio.
+    pub fn html(&self) -> String {
+        let mut output = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Schematic</title>\n<style>\n\
+             body { background: #1e1e1e; color: #808080; }\n\
+             .part { color: #4ec94e; }\n\
+             .orphan { color: #e05252; }\n\
+             .symbol { color: #e5c07b; }\n\
+             .gear { color: #d670d6; font-weight: bold; }\n\
+             </style>\n</head>\n<body>\n<p>\
+             <span class=\"part\">part</span> \
+             <span class=\"orphan\">orphan</span> \
+             <span class=\"symbol\">symbol</span> \
+             <span class=\"gear\">gear</span></p>\n<pre>\n",
+        );
+
+        for (i, row) in self.matrix.rows().enumerate() {
+            let ratios = self.gear_ratios(i);
+            for (j, c) in row.iter().enumerate() {
+                let escaped = match *c as char {
+                    '<' => "&lt;".to_string(),
+                    '>' => "&gt;".to_string(),
+                    '&' => "&amp;".to_string(),
+                    c => c.to_string(),
+                };
+                match self.kind(i, j).css_class() {
+                    Some(class) => match ratios.binary_search_by_key(&j, |(col, _)| *col) {
+                        Ok(idx) => output.push_str(&format!(
+                            "<span class=\"{class}\" title=\"gear ratio {}\">{escaped}</span>",
+                            ratios[idx].1
+                        )),
+                        Err(_) => {
+                            output.push_str(&format!("<span class=\"{class}\">{escaped}</span>"))
+                        }
+                    },
+                    None => output.push_str(&escaped),
+                }
+            }
+            output.push('\n');
+        }
+
+        output.push_str("</pre>\n</body>\n</html>\n");
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let schematic = Matrix::new("467..114..\n...*......\n..35..633.\n......#...").unwrap();
+        let renderer = Renderer::new(&schematic, '*', 2);
+
+        assert_eq!(renderer.kind(0, 0), CellKind::Part);
+        assert_eq!(renderer.kind(0, 5), CellKind::Orphan);
+        assert_eq!(renderer.kind(1, 3), CellKind::Gear);
+        assert_eq!(renderer.kind(3, 6), CellKind::Symbol);
+        assert_eq!(renderer.kind(0, 3), CellKind::Empty);
+
+        let ansi = renderer.ansi();
+        assert!(ansi.contains("\x1b[1;35m*\x1b[0m"));
+        assert!(ansi.contains("gear at 3: 16345"));
+
+        let html = renderer.html();
+        assert!(html.contains("<span class=\"gear\" title=\"gear ratio 16345\">*</span>"));
+        assert!(html.contains("<span class=\"orphan\">1</span>"));
+
+        // the gears of a row are listed in column order
+        let schematic = Matrix::new("2*3.4*5\n.......\n1*1....").unwrap();
+        let renderer = Renderer::new(&schematic, '*', 2);
+        let ansi = renderer.ansi();
+        let rows: Vec<&str> = ansi.lines().collect();
+        assert!(rows[0].ends_with("  gear at 1: 6  gear at 5: 20"));
+        assert!(!rows[1].contains("gear at"));
+        assert!(rows[2].ends_with("  gear at 1: 1"));
+        assert!(renderer.html().contains("title=\"gear ratio 20\">*</span>"));
+    }
+}
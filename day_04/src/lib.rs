@@ -1,61 +1,446 @@
 use std::{
-    collections::{HashMap, HashSet},
-    ops::Range,
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    fmt::{self, Display},
+    ops::RangeInclusive,
+    str::FromStr,
 };
 
+use lazy_static::lazy_static;
+use regex::Regex;
+
+pub mod rules;
+pub mod trace;
+
+lazy_static! {
+    pub static ref CARD_REGEX: Regex = Regex::new(
+        r"^Card\s+(?P<card_id>\d+):(?P<winning_numbers>[\d\s]*)\|(?P<my_numbers>[\d\s]*)$"
+    )
+    .unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseCardError {
+    /// The line does not look like "Card <number>: <winning numbers> | <my numbers>"
+    InvalidFormat(String),
+    /// The card number or one of the numbers does not fit into an u16
+    NumberOutOfRange(String),
+    /// The card number was already used by an earlier card
+    DuplicateCard(u16),
+}
+
+impl Display for ParseCardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseCardError::InvalidFormat(line) => write!(f, "not a card: '{line}'"),
+            ParseCardError::NumberOutOfRange(number) => {
+                write!(f, "number {number} is out of range")
+            }
+            ParseCardError::DuplicateCard(number) => write!(f, "card #{number} appears twice"),
+        }
+    }
+}
+
+impl Error for ParseCardError {}
+
+/// A `ParseCardError` together with the line it occurred in. `line_number` starts at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardPileError {
+    pub line_number: usize,
+    pub line: String,
+    pub error: ParseCardError,
+}
+
+impl Display for CardPileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {} in '{}'",
+            self.line_number, self.error, self.line
+        )
+    }
+}
+
+impl Error for CardPileError {}
+
+/// How the winning numbers and my numbers of a card are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Matching {
+    /// Duplicates are collapsed, every number matches at most once (the puzzle)
+    #[default]
+    Set,
+    /// Every duplicate counts: a number matches as often as it is on both lists
+    Multiset,
+}
+
+/// Which list of a card a number is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberList {
+    Winning,
+    Mine,
+}
+
+impl Display for NumberList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NumberList::Winning => write!(f, "the winning numbers"),
+            NumberList::Mine => write!(f, "my numbers"),
+        }
+    }
+}
+
+/// A number which is on one list of a card more than once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateNumber {
+    pub card: u16,
+    pub list: NumberList,
+    pub number: u16,
+    pub count: usize,
+}
+
+impl Display for DuplicateNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "card #{}: {} appears {} times in {}",
+            self.card, self.number, self.count, self.list
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct Card {
     pub number: u16,
+    /// Both lists as they are written on the card
+    pub winning_numbers: Vec<u16>,
+    pub my_numbers: Vec<u16>,
+    /// My numbers which are winning numbers, sorted
     pub matching_numbers: Vec<u16>,
 }
 
 impl Card {
     pub fn new(number: u16, winning_numbers: Vec<u16>, my_numbers: Vec<u16>) -> Card {
-        let winning_set: HashSet<u16> = HashSet::from_iter(winning_numbers.iter().cloned());
-        let my_set: HashSet<u16> = HashSet::from_iter(my_numbers.iter().cloned());
-        let my_wins = winning_set
-            .intersection(&my_set.clone())
-            .map(|x| *x)
-            .collect();
+        Card::with_matching(number, winning_numbers, my_numbers, Matching::Set)
+    }
+
+    pub fn with_matching(
+        number: u16,
+        winning_numbers: Vec<u16>,
+        my_numbers: Vec<u16>,
+        matching: Matching,
+    ) -> Card {
+        let mut my_wins: Vec<u16> = match matching {
+            Matching::Set => {
+                let winning_set: HashSet<u16> = HashSet::from_iter(winning_numbers.iter().cloned());
+                let my_set: HashSet<u16> = HashSet::from_iter(my_numbers.iter().cloned());
+                winning_set.intersection(&my_set).copied().collect()
+            }
+            Matching::Multiset => {
+                // every winning number can be matched once per time it is on the card
+                let mut remaining = count_numbers(&winning_numbers);
+                my_numbers
+                    .iter()
+                    .filter(|number| match remaining.get_mut(number) {
+                        Some(count) if *count > 0 => {
+                            *count -= 1;
+                            true
+                        }
+                        _ => false,
+                    })
+                    .copied()
+                    .collect()
+            }
+        };
+        my_wins.sort();
 
         Card {
             number,
+            winning_numbers,
+            my_numbers,
             matching_numbers: my_wins,
         }
     }
 
-    pub fn points(&self) -> u32 {
-        if self.matching_numbers.len() == 0 {
-            return 0;
+    /// All numbers which are on one of the lists more than once
+    pub fn duplicates(&self) -> Vec<DuplicateNumber> {
+        let mut duplicates: Vec<DuplicateNumber> = vec![];
+        for (list, numbers) in [
+            (NumberList::Winning, &self.winning_numbers),
+            (NumberList::Mine, &self.my_numbers),
+        ] {
+            for (number, count) in count_numbers(numbers) {
+                if count > 1 {
+                    duplicates.push(DuplicateNumber {
+                        card: self.number,
+                        list,
+                        number,
+                        count,
+                    });
+                }
+            }
         }
-        let base: u32 = 2;
-        base.pow(self.matching_numbers.len() as u32 - 1)
+        duplicates
     }
 
-    pub fn winning_card_numbers(&self) -> Option<Range<u16>> {
-        if self.matching_numbers.len() == 0 {
+    /// 1 point for the first match, doubled for every further match. Saturates at u64::MAX.
+    pub fn points(&self) -> u64 {
+        rules::Points::Doubling.points(self.matching_numbers.len())
+    }
+
+    /// The numbers of the next N cards for N matches. Card numbers past u16::MAX do not exist
+    /// and are cut off, so the last card wins nothing.
+    pub fn winning_card_numbers(&self) -> Option<RangeInclusive<u16>> {
+        let first = self.number as usize + 1;
+        let last = (self.number as usize + self.matching_numbers.len()).min(u16::MAX as usize);
+        if self.matching_numbers.is_empty() || first > last {
             return None;
         }
 
-        Some((self.number + 1)..(self.number + 1 + self.matching_numbers.len() as u16))
+        Some(first as u16..=last as u16)
+    }
+}
+
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        parse_card(line, Matching::Set)
+    }
+}
+
+/// Parses a single "Card <number>: <winning numbers> | <my numbers>" line.
+pub fn parse_card(line: &str, matching: Matching) -> Result<Card, ParseCardError> {
+    let captures = CARD_REGEX
+        .captures(line)
+        .ok_or_else(|| ParseCardError::InvalidFormat(line.to_string()))?;
+
+    let number = parse_number(captures.name("card_id").unwrap().as_str())?;
+    let winning_numbers = parse_numbers(captures.name("winning_numbers").unwrap().as_str())?;
+    let my_numbers = parse_numbers(captures.name("my_numbers").unwrap().as_str())?;
+
+    Ok(Card::with_matching(
+        number,
+        winning_numbers,
+        my_numbers,
+        matching,
+    ))
+}
+
+fn count_numbers(numbers: &[u16]) -> BTreeMap<u16, usize> {
+    let mut counts: BTreeMap<u16, usize> = BTreeMap::new();
+    for number in numbers {
+        *counts.entry(*number).or_default() += 1;
     }
+    counts
+}
+
+fn parse_number(input: &str) -> Result<u16, ParseCardError> {
+    input
+        .parse()
+        .map_err(|_| ParseCardError::NumberOutOfRange(input.to_string()))
+}
+
+fn parse_numbers(input: &str) -> Result<Vec<u16>, ParseCardError> {
+    input.split_whitespace().map(parse_number).collect()
+}
+
+/// Parses a pile of cards, one per line. Empty lines are skipped,
+/// every other line has to be a card with a number not used before.
+pub fn parse_cards(input: &str) -> Result<Vec<Card>, CardPileError> {
+    parse_cards_with(input, Matching::Set)
 }
 
-pub fn calculate_card_count(
-    original_cards: &HashMap<u16, Card>,
-    card: &Card,
-    mut count: usize,
-) -> usize {
-    if let Some(cards_numbers_won) = card.winning_card_numbers() {
-        println! {"-> Card #{} won {:?}", card.number, cards_numbers_won};
-        let cards_won: Vec<&Card> = cards_numbers_won
-            .map(|number| original_cards.get(&number).unwrap())
-            .collect();
+/// Like `parse_cards`, with the given way of matching the numbers of each card.
+pub fn parse_cards_with(input: &str, matching: Matching) -> Result<Vec<Card>, CardPileError> {
+    let mut cards: Vec<Card> = vec![];
+    let mut seen: HashSet<u16> = HashSet::new();
 
-        for card_copy in cards_won {
-            count = calculate_card_count(original_cards, card_copy, count + 1);
+    for (idx, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
         }
+
+        let card = parse_card(line, matching)
+            .and_then(|card| {
+                if seen.insert(card.number) {
+                    Ok(card)
+                } else {
+                    Err(ParseCardError::DuplicateCard(card.number))
+                }
+            })
+            .map_err(|error| CardPileError {
+                line_number: idx + 1,
+                line: line.to_string(),
+                error,
+            })?;
+        cards.push(card);
     }
 
-    count
+    Ok(cards)
+}
+
+/// All duplicate numbers of a pile, card by card. Useful to check a pile before choosing the matching.
+pub fn duplicates(cards: &[Card]) -> Vec<DuplicateNumber> {
+    cards.iter().flat_map(|card| card.duplicates()).collect()
+}
+
+/// How many instances of each card end up in the pile, originals included, by card number.
+///
+/// Cards only ever win copies of later cards, so every card is visited once, in card order.
+/// Copies of cards past the last card (or of missing card numbers) are not counted.
+/// Counts saturate at `usize::MAX`, see `rules::evaluate` for other rules.
+pub fn card_counts(cards: &[Card]) -> BTreeMap<u16, usize> {
+    rules::evaluate(cards, &rules::Puzzle)
+        .expect("cards only win copies of later cards")
+        .counts
+}
+
+/// The total number of cards in the pile after all copies are won (part 2), at most `usize::MAX`
+pub fn total_card_count(cards: &[Card]) -> usize {
+    rules::evaluate(cards, &rules::Puzzle)
+        .expect("cards only win copies of later cards")
+        .total()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<Card> {
+        vec![
+            Card::new(
+                1,
+                vec![41, 48, 83, 86, 17],
+                vec![83, 86, 6, 31, 17, 9, 48, 53],
+            ),
+            Card::new(
+                2,
+                vec![13, 32, 20, 16, 61],
+                vec![61, 30, 68, 82, 17, 32, 24, 19],
+            ),
+            Card::new(
+                3,
+                vec![1, 21, 53, 59, 44],
+                vec![69, 82, 63, 72, 16, 21, 14, 1],
+            ),
+            Card::new(
+                4,
+                vec![41, 92, 73, 84, 69],
+                vec![59, 84, 76, 51, 58, 5, 54, 83],
+            ),
+            Card::new(
+                5,
+                vec![87, 83, 26, 28, 32],
+                vec![88, 30, 70, 12, 93, 22, 82, 36],
+            ),
+            Card::new(
+                6,
+                vec![31, 18, 13, 56, 72],
+                vec![74, 77, 10, 23, 35, 67, 36, 11],
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_parse_cards() {
+        let input = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53\n\
+                     Card   2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19\n\n";
+        let cards = parse_cards(input).unwrap();
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[1].number, 2);
+        assert_eq!(cards.iter().map(|card| card.points()).sum::<u64>(), 10);
+
+        let err = parse_cards("Card 1: 1 | 2\nCard 2: 1 2 3\n").unwrap_err();
+        assert_eq!(err.line_number, 2);
+        assert_eq!(
+            err.error,
+            ParseCardError::InvalidFormat("Card 2: 1 2 3".to_string())
+        );
+
+        let err = parse_cards("Card 1: 1 | 2\nCard 1: 3 | 4\n").unwrap_err();
+        assert_eq!(err.line_number, 2);
+        assert_eq!(err.error, ParseCardError::DuplicateCard(1));
+
+        let err = parse_cards("Card 1: 70000 | 2\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: number 70000 is out of range in 'Card 1: 70000 | 2'"
+        );
+    }
+
+    #[test]
+    fn test_multiset_matching() {
+        let input = "Card 1: 5 5 5 7 | 5 5 7 7 9\nCard 2: 1 2 | 2 3\n";
+
+        let cards = parse_cards(input).unwrap();
+        assert_eq!(cards[0].matching_numbers, vec![5, 7]);
+        assert_eq!(cards[0].winning_numbers, vec![5, 5, 5, 7]);
+        assert_eq!(cards[0].my_numbers, vec![5, 5, 7, 7, 9]);
+
+        let cards = parse_cards_with(input, Matching::Multiset).unwrap();
+        assert_eq!(cards[0].matching_numbers, vec![5, 5, 7]);
+        assert_eq!(cards[0].points(), 4);
+
+        // lots of duplicates must not overflow the points
+        let fives = vec![5; 100];
+        let card = Card::with_matching(3, fives.clone(), fives, Matching::Multiset);
+        assert_eq!(card.matching_numbers.len(), 100);
+        assert_eq!(card.points(), u64::MAX);
+        let fives = vec![5; 40];
+        let card = Card::with_matching(4, fives.clone(), fives, Matching::Multiset);
+        assert_eq!(card.points(), 1 << 39);
+        assert_eq!(cards[1].matching_numbers, vec![2]);
+
+        let found: Vec<String> = duplicates(&cards).iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            found,
+            vec![
+                "card #1: 5 appears 3 times in the winning numbers",
+                "card #1: 5 appears 2 times in my numbers",
+                "card #1: 7 appears 2 times in my numbers",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_card_counts() {
+        let mut cards = sample();
+        assert_eq!(cards.iter().map(|card| card.points()).sum::<u64>(), 13);
+
+        // the order of the cards does not matter
+        cards.reverse();
+        let counts: Vec<(u16, usize)> = card_counts(&cards).into_iter().collect();
+        assert_eq!(
+            counts,
+            vec![(1, 1), (2, 2), (3, 4), (4, 8), (5, 14), (6, 1)]
+        );
+        assert_eq!(total_card_count(&cards), 30);
+    }
+
+    #[test]
+    fn test_wins_past_last_card() {
+        let cards = vec![
+            Card::new(1, vec![1, 2, 3], vec![1, 2, 3]),
+            Card::new(2, vec![1, 2], vec![1, 2]),
+        ];
+        assert_eq!(total_card_count(&cards), 1 + 2);
+
+        let card = Card::new(u16::MAX, vec![1], vec![1]);
+        assert_eq!(card.winning_card_numbers(), None);
+        assert_eq!(total_card_count(&[card]), 1);
+
+        // the second to last card still wins the last one, more matches are cut off
+        let cards = vec![
+            Card::new(u16::MAX - 1, vec![1, 2, 3], vec![1, 2, 3]),
+            Card::new(u16::MAX, vec![], vec![]),
+        ];
+        assert_eq!(cards[0].winning_card_numbers(), Some(u16::MAX..=u16::MAX));
+        let counts: Vec<(u16, usize)> = card_counts(&cards).into_iter().collect();
+        assert_eq!(counts, vec![(u16::MAX - 1, 1), (u16::MAX, 2)]);
+
+        // more than u16::MAX matches do not wrap around
+        let numbers: Vec<u16> = (0..=u16::MAX).collect();
+        let card = Card::new(1, numbers.clone(), numbers);
+        assert_eq!(card.winning_card_numbers(), Some(2..=u16::MAX));
+    }
 }
@@ -1,48 +1,78 @@
-use std::collections::HashMap;
-
-use day_04::{calculate_card_count, Card};
-use regex::Regex;
+use day_04::{
+    card_counts, duplicates, parse_cards_with,
+    rules::{evaluate, Variant},
+    trace::{to_json, trace},
+    Matching,
+};
 
 fn main() {
     let input_filename = "input2";
     let input = std::fs::read_to_string(input_filename).unwrap();
 
-    let re = Regex::new(
-        r"Card\s+(?P<card_id>\d+):\s+(?P<wining_numbers>(\d+|\s+)+)\|\s+(?P<my_numbers>(\d+|\s+)+)$",
-    )
-    .unwrap();
-
-    let mut cards: HashMap<u16, Card> = HashMap::new();
-
-    let mut result = 0;
-    for line in input.lines() {
-        for capture in re.captures_iter(line) {
-            let card_id: u16 = capture.name("card_id").unwrap().as_str().parse().unwrap();
-            let winning_numbers_string = capture.name("wining_numbers").unwrap().as_str();
-            let my_numbers_string = capture.name("my_numbers").unwrap().as_str();
-
-            let winning_numbers: Vec<u16> = winning_numbers_string
-                .split_whitespace()
-                .map(|x| x.parse().unwrap())
-                .collect();
-            let my_numbers: Vec<u16> = my_numbers_string
-                .split_whitespace()
-                .map(|x| x.parse().unwrap())
-                .collect();
-
-            let card = Card::new(card_id, winning_numbers, my_numbers);
-
-            result += card.points();
-            cards.insert(card.number, card);
+    // '--multiset' counts duplicate numbers on a card, '--validate' lists them
+    let args: Vec<String> = std::env::args().collect();
+    let matching = if args.iter().any(|arg| arg == "--multiset") {
+        Matching::Multiset
+    } else {
+        Matching::Set
+    };
+
+    let cards = match parse_cards_with(&input, matching) {
+        Ok(cards) => cards,
+        Err(e) => panic!("cannot parse cards: {e}"),
+    };
+
+    if args.iter().any(|arg| arg == "--validate") {
+        let found = duplicates(&cards);
+        for duplicate in &found {
+            println!("{duplicate}");
         }
+        println!("=> Found {} duplicate numbers", found.len());
     }
+
+    let result: u64 = cards
+        .iter()
+        .map(|card| card.points())
+        .fold(0, u64::saturating_add);
     println!("=> Result is: {result}");
 
-    let mut card_count = 0;
-    for (_, card) in &cards {
-        println! {"======= Card #{} -> {:?}", card.number, card.winning_card_numbers()};
-        card_count += calculate_card_count(&cards, &card, 0);
+    let counts = card_counts(&cards);
+    for (number, count) in &counts {
+        println! {"======= Card #{} -> {} instances", number, count};
     }
-    card_count += cards.len();
+    let card_count: usize = counts.values().copied().fold(0, usize::saturating_add);
     println!("=> Card count is: {card_count}");
+
+    // '--points <formula>', '--offset <cards>' and '--wrap' play a variant of the game
+    let mut variant = Variant::default();
+    if let Some(pos) = args.iter().position(|arg| arg == "--points") {
+        let points = args.get(pos + 1).expect("--points needs a formula");
+        variant.points = points.parse().unwrap_or_else(|e| panic!("{e}"));
+    }
+    if let Some(pos) = args.iter().position(|arg| arg == "--offset") {
+        let offset = args.get(pos + 1).expect("--offset needs a number of cards");
+        variant.copies.offset = offset.parse().expect("invalid offset");
+    }
+    variant.copies.wrap = args.iter().any(|arg| arg == "--wrap");
+
+    if variant != Variant::default() {
+        match evaluate(&cards, &variant) {
+            Ok(evaluation) => println!(
+                "=> Variant {:?}: {} points, {} cards",
+                variant,
+                evaluation.points,
+                evaluation.total()
+            ),
+            Err(e) => println!("=> Variant {variant:?}: {e}"),
+        }
+    }
+
+    // '--trace' shows where the copies came from, '--json' prints it as JSON
+    if args.iter().any(|arg| arg == "--trace") {
+        match trace(&cards, &variant) {
+            Ok(trace) if args.iter().any(|arg| arg == "--json") => println!("{}", to_json(&trace)),
+            Ok(trace) => println!("{trace}"),
+            Err(e) => println!("=> Cannot trace: {e}"),
+        }
+    }
 }
@@ -0,0 +1,362 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::Card;
+
+/// The rules of a scratchcard game: how many points a card is worth and which cards it wins copies of.
+pub trait ScoringRule {
+    /// Points of a card with the given number of matching numbers
+    fn points(&self, matches: usize) -> u64;
+
+    /// The card numbers `card` wins a copy of. `pile` are the numbers of all cards, sorted.
+    /// A number may be won more than once, numbers which are not in the pile are ignored.
+    fn won_cards(&self, card: &Card, pile: &[u16]) -> Vec<u16>;
+}
+
+/// The rules of the puzzle: doubling points, and a copy of each of the next N cards.
+pub struct Puzzle;
+
+impl ScoringRule for Puzzle {
+    fn points(&self, matches: usize) -> u64 {
+        Points::Doubling.points(matches)
+    }
+
+    fn won_cards(&self, card: &Card, pile: &[u16]) -> Vec<u16> {
+        match card.winning_card_numbers() {
+            Some(numbers) => numbers
+                .filter(|number| pile.binary_search(number).is_ok())
+                .collect(),
+            None => vec![],
+        }
+    }
+}
+
+/// Point formulas, by the number of matches. No matches are always worth 0 points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Points {
+    /// 1, 2, 4, 8, ...
+    Doubling,
+    /// The given points per match
+    Linear(u64),
+    /// 1, 1, 2, 3, 5, ...
+    Fibonacci,
+    /// points[matches - 1], the last entry is used for more matches than the table has
+    Table(Vec<u64>),
+}
+
+impl Points {
+    pub fn points(&self, matches: usize) -> u64 {
+        if matches == 0 {
+            return 0;
+        }
+
+        match self {
+            Points::Doubling => 1u64.checked_shl(matches as u32 - 1).unwrap_or(u64::MAX),
+            Points::Linear(per_match) => per_match.saturating_mul(matches as u64),
+            Points::Fibonacci => {
+                let (mut a, mut b) = (0u64, 1u64);
+                for _ in 1..matches {
+                    (a, b) = (b, a.saturating_add(b));
+                }
+                b
+            }
+            Points::Table(points) => match points.get(matches - 1).or(points.last()) {
+                Some(points) => *points,
+                None => 0,
+            },
+        }
+    }
+}
+
+/// Parses "doubling", "linear:<points>", "fibonacci" or "table:<points>,<points>,...".
+impl FromStr for Points {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = input.split_once(':').unwrap_or((input, ""));
+        match name {
+            "doubling" => Ok(Points::Doubling),
+            "fibonacci" => Ok(Points::Fibonacci),
+            "linear" => arg
+                .parse()
+                .map(Points::Linear)
+                .map_err(|_| format!("invalid points per match '{arg}'")),
+            "table" => arg
+                .split(',')
+                .map(|points| points.trim().parse())
+                .collect::<Result<_, _>>()
+                .map(Points::Table)
+                .map_err(|_| format!("invalid points table '{arg}'")),
+            _ => Err(format!("unknown point formula '{input}'")),
+        }
+    }
+}
+
+/// Wins a copy of N consecutive cards for N matches, starting `offset` cards after the card itself.
+///
+/// The cards are counted by number. With `wrap`, the cards past the last card continue at the first card.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Copies {
+    pub offset: usize,
+    pub wrap: bool,
+}
+
+impl Default for Copies {
+    fn default() -> Self {
+        Copies {
+            offset: 1,
+            wrap: false,
+        }
+    }
+}
+
+impl Copies {
+    pub fn won_cards(&self, card: &Card, pile: &[u16]) -> Vec<u16> {
+        let (Some(first), Some(last)) = (pile.first(), pile.last()) else {
+            return vec![];
+        };
+        let (first, last) = (*first as usize, *last as usize);
+
+        let start = card.number as usize + self.offset;
+        (start..start + card.matching_numbers.len())
+            .filter_map(|number| {
+                if self.wrap && number > last {
+                    Some(first + (number - first) % (last - first + 1))
+                } else if number <= last {
+                    Some(number)
+                } else {
+                    None
+                }
+            })
+            .map(|number| number as u16)
+            .filter(|number| pile.binary_search(number).is_ok())
+            .collect()
+    }
+}
+
+/// A game variant built from a point formula and a copy rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub points: Points,
+    pub copies: Copies,
+}
+
+impl Default for Variant {
+    fn default() -> Self {
+        Variant {
+            points: Points::Doubling,
+            copies: Copies::default(),
+        }
+    }
+}
+
+impl ScoringRule for Variant {
+    fn points(&self, matches: usize) -> u64 {
+        self.points.points(matches)
+    }
+
+    fn won_cards(&self, card: &Card, pile: &[u16]) -> Vec<u16> {
+        self.copies.won_cards(card, pile)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CascadeError {
+    /// The cards win copies of each other in a cycle, so the pile never stops growing.
+    /// Contains all cards which are part of a cycle or are won through one.
+    Unbounded(Vec<u16>),
+}
+
+impl Display for CascadeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CascadeError::Unbounded(cards) => {
+                write!(f, "cards {cards:?} win copies of each other endlessly")
+            }
+        }
+    }
+}
+
+impl Error for CascadeError {}
+
+/// The outcome of a pile under a scoring rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    /// Points of the original cards (part 1)
+    pub points: u64,
+    /// Instances of each card after all copies are won, originals included, at most `usize::MAX`
+    pub counts: BTreeMap<u16, usize>,
+}
+
+impl Evaluation {
+    /// The number of cards in the pile after all copies are won (part 2), at most `usize::MAX`
+    pub fn total(&self) -> usize {
+        self.counts.values().copied().fold(0, usize::saturating_add)
+    }
+}
+
+// The card numbers won by each card
+pub(crate) type WonCards = BTreeMap<u16, Vec<u16>>;
+
+/// The cards each card wins copies of, and an order in which every card comes after all cards
+/// winning copies of it. Fails if the rule lets the cards win each other endlessly.
+pub(crate) fn cascade<R: ScoringRule + ?Sized>(
+    cards: &[Card],
+    rule: &R,
+) -> Result<(WonCards, Vec<u16>), CascadeError> {
+    let mut pile: Vec<u16> = cards.iter().map(|card| card.number).collect();
+    pile.sort();
+
+    let mut won: WonCards = BTreeMap::new();
+    let mut pending: BTreeMap<u16, usize> = cards.iter().map(|card| (card.number, 0)).collect();
+    for card in cards {
+        let mut numbers = rule.won_cards(card, &pile);
+        numbers.retain(|number| pending.contains_key(number));
+        for number in &numbers {
+            *pending.get_mut(number).unwrap() += 1;
+        }
+        won.insert(card.number, numbers);
+    }
+
+    let mut order: Vec<u16> = vec![];
+    let mut ready: BTreeSet<u16> = pending
+        .iter()
+        .filter(|(_, pending)| **pending == 0)
+        .map(|(number, _)| *number)
+        .collect();
+    while let Some(number) = ready.pop_first() {
+        for other in &won[&number] {
+            let pending = pending.get_mut(other).unwrap();
+            *pending -= 1;
+            if *pending == 0 {
+                ready.insert(*other);
+            }
+        }
+        order.push(number);
+    }
+
+    let unbounded: Vec<u16> = pending
+        .into_iter()
+        .filter(|(_, pending)| *pending > 0)
+        .map(|(number, _)| number)
+        .collect();
+    if !unbounded.is_empty() {
+        return Err(CascadeError::Unbounded(unbounded));
+    }
+
+    Ok((won, order))
+}
+
+/// Scores the pile and plays out all copies under the rule.
+///
+/// Each card is processed once, after all cards winning copies of it are done, so the copies
+/// are never played out one by one. Fails if the rule lets the cards win each other endlessly.
+/// Like the points, the counts saturate instead of overflowing.
+pub fn evaluate<R: ScoringRule + ?Sized>(
+    cards: &[Card],
+    rule: &R,
+) -> Result<Evaluation, CascadeError> {
+    let points = cards
+        .iter()
+        .map(|card| rule.points(card.matching_numbers.len()))
+        .fold(0u64, |acc, points| acc.saturating_add(points));
+
+    let (won, order) = cascade(cards, rule)?;
+    let mut counts: BTreeMap<u16, usize> = cards.iter().map(|card| (card.number, 1)).collect();
+    for number in order {
+        let copies = counts[&number];
+        for other in &won[&number] {
+            let count = counts.get_mut(other).unwrap();
+            *count = count.saturating_add(copies);
+        }
+    }
+
+    Ok(Evaluation { points, counts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pile(matches: &[usize]) -> Vec<Card> {
+        matches
+            .iter()
+            .enumerate()
+            .map(|(idx, matches)| {
+                let numbers: Vec<u16> = (0..*matches as u16).collect();
+                Card::new(idx as u16 + 1, numbers.clone(), numbers)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_points() {
+        let counts: Vec<u64> = (0..7).map(|n| Points::Fibonacci.points(n)).collect();
+        assert_eq!(counts, vec![0, 1, 1, 2, 3, 5, 8]);
+        assert_eq!(Points::Linear(3).points(4), 12);
+        assert_eq!(Points::Doubling.points(100), u64::MAX);
+        assert_eq!("table:1,5,10".parse::<Points>().unwrap().points(2), 5);
+        assert_eq!("table:1,5,10".parse::<Points>().unwrap().points(7), 10);
+        assert!("linear:x".parse::<Points>().is_err());
+    }
+
+    #[test]
+    fn test_saturating_counts() {
+        // each card wins the next 10, so the counts grow roughly like 2^n
+        let cards = pile(&[10; 100]);
+        let evaluation = evaluate(&cards, &Puzzle).unwrap();
+        assert_eq!(evaluation.points, 100 * 512);
+        assert_eq!(evaluation.counts[&100], usize::MAX);
+        assert_eq!(evaluation.total(), usize::MAX);
+        assert_eq!(crate::total_card_count(&cards), usize::MAX);
+
+        let trace = crate::trace::trace(&cards, &Puzzle).unwrap();
+        assert_eq!(trace.total, usize::MAX);
+        assert_eq!(trace.cards[99].instances, usize::MAX);
+        assert_eq!(trace.cards[98].contributed, usize::MAX);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        // the puzzle example, by number of matches
+        let cards = pile(&[4, 2, 2, 1, 0, 0]);
+        let puzzle = evaluate(&cards, &Puzzle).unwrap();
+        assert_eq!((puzzle.points, puzzle.total()), (13, 30));
+        assert_eq!(evaluate(&cards, &Variant::default()).unwrap(), puzzle);
+
+        let variant = Variant {
+            points: Points::Linear(1),
+            copies: Copies {
+                offset: 2,
+                wrap: false,
+            },
+        };
+        let evaluation = evaluate(&cards, &variant).unwrap();
+        assert_eq!(evaluation.points, 9);
+        // 1 wins 3..=6, 2 wins 4 and 5, 3 wins 5 and 6, 4 wins 6
+        let counts: Vec<usize> = evaluation.counts.values().copied().collect();
+        assert_eq!(counts, vec![1, 1, 2, 3, 5, 7]);
+
+        let wrapping = Variant {
+            copies: Copies {
+                offset: 1,
+                wrap: true,
+            },
+            ..Variant::default()
+        };
+        let cards = pile(&[0, 1, 2]);
+        // 3 wins 1 and 2, 2 wins 3
+        assert!(Copies::default()
+            .won_cards(&cards[2], &[1, 2, 3])
+            .is_empty());
+        assert_eq!(wrapping.won_cards(&cards[2], &[1, 2, 3]), vec![1, 2]);
+        assert_eq!(
+            evaluate(&cards, &wrapping).unwrap_err(),
+            CascadeError::Unbounded(vec![1, 2, 3])
+        );
+    }
+}
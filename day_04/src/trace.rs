@@ -0,0 +1,229 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+use serde::Serialize;
+
+use crate::{
+    rules::{cascade, CascadeError, ScoringRule},
+    Card,
+};
+
+/// Where the instances of a single card came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CardTrace {
+    pub number: u16,
+    /// Instances in the final pile, the original included
+    pub instances: usize,
+    /// Copies won of this card by each other card
+    pub sources: BTreeMap<u16, usize>,
+    /// Copies of other cards won by all instances of this card
+    pub contributed: usize,
+    /// The longest chain of wins leading to a copy of this card, 0 if no card wins it
+    pub depth: usize,
+}
+
+impl CardTrace {
+    pub fn copies(&self) -> usize {
+        self.instances - 1
+    }
+}
+
+/// A full simulation of a pile: every card with the provenance of its copies,
+/// and how many instances were produced at each step of the cascade.
+/// All counts saturate at `usize::MAX`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Trace {
+    /// All cards, by number
+    pub cards: Vec<CardTrace>,
+    /// generations[i] are the instances won through a chain of i wins, generations[0] are the originals
+    pub generations: Vec<usize>,
+    pub total: usize,
+}
+
+impl Trace {
+    /// The longest chain of wins in the pile
+    pub fn depth(&self) -> usize {
+        self.generations.len().saturating_sub(1)
+    }
+
+    /// Up to `n` cards which won the most copies, most copies first and by number on ties
+    pub fn top_contributors(&self, n: usize) -> Vec<&CardTrace> {
+        let mut cards: Vec<&CardTrace> = self
+            .cards
+            .iter()
+            .filter(|card| card.contributed > 0)
+            .collect();
+        cards.sort_by_key(|card| (std::cmp::Reverse(card.contributed), card.number));
+        cards.truncate(n);
+        cards
+    }
+
+    /// Histogram of the cards by their number of instances
+    pub fn instances_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram: BTreeMap<usize, usize> = BTreeMap::new();
+        for card in &self.cards {
+            *histogram.entry(card.instances).or_default() += 1;
+        }
+        histogram
+    }
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} cards, {} instances after a cascade of depth {}",
+            self.cards.len(),
+            self.total,
+            self.depth()
+        )?;
+
+        writeln!(f, "Instances per card:")?;
+        for card in &self.cards {
+            let sources: Vec<String> = card
+                .sources
+                .iter()
+                .map(|(number, copies)| format!("{copies} from #{number}"))
+                .collect();
+            write!(
+                f,
+                "  Card #{:<4} {:>8} instances, depth {:>3}, won {:>8} copies",
+                card.number, card.instances, card.depth, card.contributed
+            )?;
+            if !sources.is_empty() {
+                write!(f, " ({})", sources.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "Instances per generation:")?;
+        for (generation, instances) in self.generations.iter().enumerate() {
+            writeln!(f, "  {generation:>3} wins: {instances}")?;
+        }
+
+        writeln!(f, "Histogram of instances:")?;
+        for (instances, cards) in self.instances_histogram() {
+            writeln!(f, "  {instances:>8} instances: {cards} cards")?;
+        }
+
+        write!(f, "Top contributors:")?;
+        for card in self.top_contributors(5) {
+            write!(f, "\n  Card #{}: {} copies", card.number, card.contributed)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plays out all copies of the pile under the rule, keeping track of where every copy came from.
+pub fn trace<R: ScoringRule + ?Sized>(cards: &[Card], rule: &R) -> Result<Trace, CascadeError> {
+    let (won, order) = cascade(cards, rule)?;
+
+    // generations[card][i] are the instances of the card won through a chain of i wins
+    let mut generations: BTreeMap<u16, Vec<usize>> =
+        cards.iter().map(|card| (card.number, vec![1])).collect();
+    let mut sources: BTreeMap<u16, BTreeMap<u16, usize>> = BTreeMap::new();
+    for number in order {
+        let instances = generations[&number].clone();
+        let copies = instances
+            .iter()
+            .fold(0, |acc: usize, count| acc.saturating_add(*count));
+        for other in &won[&number] {
+            let source = sources
+                .entry(*other)
+                .or_default()
+                .entry(number)
+                .or_default();
+            *source = source.saturating_add(copies);
+
+            let target = generations.get_mut(other).unwrap();
+            if target.len() < instances.len() + 1 {
+                target.resize(instances.len() + 1, 0);
+            }
+            for (i, count) in instances.iter().enumerate() {
+                target[i + 1] = target[i + 1].saturating_add(*count);
+            }
+        }
+    }
+
+    let mut total_generations: Vec<usize> = vec![];
+    let mut traces: Vec<CardTrace> = vec![];
+    for (number, card_generations) in &generations {
+        if total_generations.len() < card_generations.len() {
+            total_generations.resize(card_generations.len(), 0);
+        }
+        for (i, count) in card_generations.iter().enumerate() {
+            total_generations[i] = total_generations[i].saturating_add(*count);
+        }
+
+        let instances = card_generations
+            .iter()
+            .fold(0, |acc: usize, count| acc.saturating_add(*count));
+        traces.push(CardTrace {
+            number: *number,
+            instances,
+            sources: sources.remove(number).unwrap_or_default(),
+            contributed: instances.saturating_mul(won[number].len()),
+            depth: card_generations.len() - 1,
+        });
+    }
+
+    Ok(Trace {
+        total: total_generations
+            .iter()
+            .fold(0, |acc, count| acc.saturating_add(*count)),
+        cards: traces,
+        generations: total_generations,
+    })
+}
+
+pub fn to_json(trace: &Trace) -> String {
+    serde_json::to_string_pretty(trace).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_cards, rules::Puzzle};
+
+    #[test]
+    fn test_trace() {
+        let input = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53\n\
+                     Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19\n\
+                     Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1\n\
+                     Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83\n\
+                     Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36\n\
+                     Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+        let cards = parse_cards(input).unwrap();
+        let trace = trace(&cards, &Puzzle).unwrap();
+
+        assert_eq!(trace.total, 30);
+        let instances: Vec<usize> = trace.cards.iter().map(|card| card.instances).collect();
+        assert_eq!(instances, vec![1, 2, 4, 8, 14, 1]);
+
+        // card 5 is won by 1, 3 and 4: 1 + 4 + 8 copies
+        assert_eq!(
+            trace.cards[4].sources,
+            BTreeMap::from([(1, 1), (3, 4), (4, 8)])
+        );
+        assert_eq!(trace.cards[4].copies(), 13);
+        assert_eq!(trace.cards[4].depth, 4);
+        assert_eq!(trace.depth(), 4);
+        // the originals, then the copies won by them, the copies won by those copies, ...
+        assert_eq!(trace.generations, vec![6, 9, 9, 5, 1]);
+
+        let top: Vec<(u16, usize)> = trace
+            .top_contributors(2)
+            .iter()
+            .map(|card| (card.number, card.contributed))
+            .collect();
+        assert_eq!(top, vec![(3, 8), (4, 8)]);
+        assert_eq!(trace.top_contributors(10).len(), 4);
+        assert_eq!(trace.instances_histogram()[&1], 2);
+
+        let json: serde_json::Value = serde_json::from_str(&to_json(&trace)).unwrap();
+        assert_eq!(json["cards"][4]["sources"]["4"], 8);
+        assert!(trace.to_string().contains("Card #5"));
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    fmt::{self, Display},
+    ops::Range,
+};
+
+use crate::{Mapping, ParseAlmanacError};
+
+/// One section of the almanac, e.g. seed-to-soil: non-overlapping mappings sorted by their source range.
+///
+/// Keys which are not covered by any mapping map to themselves.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IntervalMap {
+    mappings: Vec<Mapping>,
+}
+
+impl IntervalMap {
+    pub fn new(mut mappings: Vec<Mapping>) -> Result<IntervalMap, ParseAlmanacError> {
+        mappings.retain(|mapping| mapping.length > 0);
+        mappings.sort_by_key(|mapping| mapping.source_start);
+
+        for pair in mappings.windows(2) {
+            if pair[0].source_range().end > pair[1].source_start {
+                return Err(ParseAlmanacError::Overlap(pair[0], pair[1]));
+            }
+        }
+
+        Ok(IntervalMap { mappings })
+    }
+
+    /// Builds the map from mapping lines like "50 98 2". A "<name> map:" header line is skipped.
+    pub fn from_lines(section: &str) -> Result<IntervalMap, ParseAlmanacError> {
+        let mappings = section
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.ends_with("map:"))
+            .map(|line| line.parse())
+            .collect::<Result<Vec<Mapping>, _>>()?;
+
+        IntervalMap::new(mappings)
+    }
+
+    /// All mappings, ordered by their source range
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    pub fn get(&self, key: u64) -> u64 {
+        // the last mapping starting at or before the key is the only one which may contain it
+        let idx = self
+            .mappings
+            .partition_point(|mapping| mapping.source_start <= key);
+        if idx == 0 {
+            return key;
+        }
+
+        self.mappings[idx - 1]
+            .map_source_to_destination(key)
+            .unwrap_or(key)
+    }
+
+    /// Maps every key of the range. The result holds one range per mapping (or gap) the range overlaps,
+    /// ordered by their source keys.
+    pub fn map_range(&self, range: Range<u64>) -> Vec<Range<u64>> {
+        let mut output: Vec<Range<u64>> = vec![];
+        let mut cursor = range.start;
+
+        // skip all mappings which end before the range
+        let first = self
+            .mappings
+            .partition_point(|mapping| mapping.source_range().end <= range.start);
+        for mapping in &self.mappings[first..] {
+            if cursor >= range.end || mapping.source_start >= range.end {
+                break;
+            }
+
+            // a gap before the mapping maps to itself
+            if cursor < mapping.source_start {
+                output.push(cursor..mapping.source_start);
+                cursor = mapping.source_start;
+            }
+
+            let end = mapping.source_range().end.min(range.end);
+            let offset = cursor - mapping.source_start;
+            let start = mapping.destination_start + offset;
+            output.push(start..start + (end - cursor));
+            cursor = end;
+        }
+
+        if cursor < range.end {
+            output.push(cursor..range.end);
+        }
+
+        output
+    }
+
+    /// The map which is equivalent to this map followed by `next`, e.g. seed-to-fertilizer
+    /// from seed-to-soil and soil-to-fertilizer.
+    pub fn compose(&self, next: &IntervalMap) -> IntervalMap {
+        let mut mappings: Vec<Mapping> = vec![];
+
+        // every piece of this map, gaps included, is split up where the mappings of `next` end
+        for (source, destination) in self.pieces() {
+            let mut source_start = source.start;
+            for output in next.map_range(destination) {
+                let length = output.end - output.start;
+                let mapping = Mapping::new(source_start, output.start, length);
+                source_start += length;
+
+                // identity is the default, and neighbouring pieces which continue each other are merged
+                if mapping.source_start == mapping.destination_start {
+                    continue;
+                }
+                match mappings.last_mut() {
+                    Some(last)
+                        if last.source_range().end == mapping.source_start
+                            && last.destination_range().end == mapping.destination_start =>
+                    {
+                        last.length += length
+                    }
+                    _ => mappings.push(mapping),
+                }
+            }
+        }
+
+        IntervalMap { mappings }
+    }
+
+    // All keys split up into the mappings and the gaps between them, as source and destination ranges
+    fn pieces(&self) -> Vec<(Range<u64>, Range<u64>)> {
+        let mut pieces: Vec<(Range<u64>, Range<u64>)> = vec![];
+        let mut cursor = 0;
+        for mapping in &self.mappings {
+            if cursor < mapping.source_start {
+                pieces.push((cursor..mapping.source_start, cursor..mapping.source_start));
+            }
+            pieces.push((mapping.source_range(), mapping.destination_range()));
+            cursor = mapping.source_range().end;
+        }
+        if cursor < u64::MAX {
+            pieces.push((cursor..u64::MAX, cursor..u64::MAX));
+        }
+        pieces
+    }
+
+    /// Maps all ranges, see `map_range`
+    pub fn map_ranges(&self, ranges: &[Range<u64>]) -> Vec<Range<u64>> {
+        ranges
+            .iter()
+            .flat_map(|range| self.map_range(range.clone()))
+            .collect()
+    }
+}
+
+/// Composes all maps into a single one, e.g. seed-to-location from all sections of the almanac.
+pub fn compose_all(maps: &[IntervalMap]) -> IntervalMap {
+    maps.iter().fold(IntervalMap::default(), |composed, map| {
+        composed.compose(map)
+    })
+}
+
+/// Prints the mappings like an almanac section, one "<destination start> <source start> <length>" per line.
+impl Display for IntervalMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for mapping in &self.mappings {
+            writeln!(f, "{mapping}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_map() {
+        let map = IntervalMap::from_lines("seed-to-soil map:\n50 98 2\n52 50 48").unwrap();
+        assert_eq!(map.mappings()[0].source_range(), 50..98);
+        assert_eq!(map.mappings()[1].destination_range(), 50..52);
+
+        let keys: Vec<u64> = [0, 49, 50, 51, 97, 98, 99, 100]
+            .map(|key| map.get(key))
+            .to_vec();
+        assert_eq!(keys, vec![0, 49, 52, 53, 99, 50, 51, 100]);
+
+        assert_eq!(map.map_range(40..60), vec![40..50, 52..62]);
+        assert_eq!(map.map_range(90..110), vec![92..100, 50..52, 100..110]);
+        assert_eq!(map.map_range(98..99), vec![50..51]);
+        assert_eq!(map.map_range(0..10), vec![0..10]);
+        assert!(map.map_range(5..5).is_empty());
+
+        // every key of the range is mapped, and nothing else
+        let mut mapped: Vec<u64> = map.map_range(30..120).into_iter().flatten().collect();
+        let mut expected: Vec<u64> = (30..120).map(|key| map.get(key)).collect();
+        mapped.sort();
+        expected.sort();
+        assert_eq!(mapped, expected);
+
+        assert_eq!(
+            IntervalMap::from_lines("50 98 2\n52 97 48"),
+            Err(ParseAlmanacError::Overlap(
+                Mapping::new(97, 52, 48),
+                Mapping::new(98, 50, 2)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compose() {
+        let first = IntervalMap::from_lines("50 98 2\n52 50 48").unwrap();
+        let second = IntervalMap::from_lines("0 15 37\n37 52 2\n39 0 15").unwrap();
+        let composed = first.compose(&second);
+
+        for key in 0..200 {
+            assert_eq!(composed.get(key), second.get(first.get(key)), "key {key}");
+        }
+        assert_eq!(composed.get(u64::MAX), u64::MAX);
+
+        // the composition of nothing is the identity, which has no mappings
+        assert_eq!(compose_all(&[]), IntervalMap::default());
+        assert_eq!(compose_all(&[first.clone(), second.clone()]), composed);
+
+        // the printed mappings are a valid section again
+        assert_eq!(
+            IntervalMap::from_lines(&composed.to_string()).unwrap(),
+            composed
+        );
+
+        let inverse = IntervalMap::from_lines("98 50 2\n50 52 48").unwrap();
+        assert!(first.compose(&inverse).mappings().is_empty());
+    }
+}
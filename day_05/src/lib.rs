@@ -1,13 +1,63 @@
-use std::{collections::HashMap, error::Error, ops::Range, u64};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    ops::Range,
+    str::FromStr,
+};
+
+pub mod interval_map;
+
+use interval_map::{compose_all, IntervalMap};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseAlmanacError {
+    /// The first line does not look like "seeds: <numbers>"
+    InvalidSeeds(String),
+    /// The line does not look like "<destination start> <source start> <length>",
+    /// or one of its ranges does not fit into an u64
+    InvalidMapping(String),
+    /// Two mappings of the same section map some keys both
+    Overlap(Mapping, Mapping),
+}
+
+impl Display for ParseAlmanacError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseAlmanacError::InvalidSeeds(line) => write!(f, "not a list of seeds: '{line}'"),
+            ParseAlmanacError::InvalidMapping(line) => write!(f, "not a mapping: '{line}'"),
+            ParseAlmanacError::Overlap(a, b) => write!(f, "mappings '{a}' and '{b}' overlap"),
+        }
+    }
+}
+
+impl Error for ParseAlmanacError {}
 
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use regex::Regex;
+/// A `ParseAlmanacError` together with the line it occurred in. `line_number` starts at 1,
+/// for overlapping mappings it is the header line of their section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlmanacError {
+    pub line_number: usize,
+    pub line: String,
+    pub error: ParseAlmanacError,
+}
+
+impl Display for AlmanacError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {} in '{}'",
+            self.line_number, self.error, self.line
+        )
+    }
+}
+
+impl Error for AlmanacError {}
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Mapping {
-    source_start: u64,
-    destination_start: u64,
-    length: u64,
+    pub source_start: u64,
+    pub destination_start: u64,
+    pub length: u64,
 }
 
 impl Mapping {
@@ -20,24 +70,15 @@ impl Mapping {
     }
 
     pub fn source_range(&self) -> Range<u64> {
-        self.source_start..self.source_start + self.length - 1
+        self.source_start..self.source_start + self.length
     }
 
     pub fn destination_range(&self) -> Range<u64> {
-        self.destination_start..self.destination_start + self.length - 1
+        self.destination_start..self.destination_start + self.length
     }
 
     pub fn new_from_string(line: &str) -> Option<Mapping> {
-        let re =
-            Regex::new(r"(?P<dst_range_start>\d+)\s*(?P<src_range_start>\d+)\s*(?P<length>\d+)")
-                .unwrap();
-
-        let capt = re.captures(line)?;
-        let dst_range_start: u64 = capt.name("dst_range_start")?.as_str().parse().unwrap();
-        let src_range_start: u64 = capt.name("src_range_start")?.as_str().parse().unwrap();
-        let length: u64 = capt.name("length")?.as_str().parse().unwrap();
-
-        Some(Mapping::new(src_range_start, dst_range_start, length))
+        line.parse().ok()
     }
 
     pub fn map_source_to_destination(&self, key: u64) -> Option<u64> {
@@ -61,139 +102,244 @@ impl Mapping {
     }
 }
 
-pub fn min_value(mappings: &Vec<Mapping>) -> u64 {
-    let dest_start: Vec<u64> = mappings.par_iter().map(|m| m.destination_start).collect();
-    dest_start.par_iter().min().unwrap().clone()
+/// Prints the mapping like the almanac: "<destination start> <source start> <length>".
+impl Display for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.destination_start, self.source_start, self.length
+        )
+    }
 }
 
-pub fn get_mapping_by_dest(mappings: &Vec<Mapping>, dst_value: u64) -> Option<&Mapping> {
-    for map in mappings {
-        if map.has_destination_value(dst_value) {
-            return Some(map);
-        }
+impl FromStr for Mapping {
+    type Err = ParseAlmanacError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseAlmanacError::InvalidMapping(line.to_string());
+
+        let numbers: Vec<u64> = line
+            .split_whitespace()
+            .map(|number| number.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+        let [destination_start, source_start, length] = numbers[..] else {
+            return Err(invalid());
+        };
+
+        // both ranges have to fit into an u64
+        source_start.checked_add(length).ok_or_else(invalid)?;
+        destination_start.checked_add(length).ok_or_else(invalid)?;
+
+        Ok(Mapping::new(source_start, destination_start, length))
     }
+}
 
-    None
+/// The seeds and all sections of the almanac, in the order seed-to-soil, ..., humidity-to-location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Almanac {
+    pub seeds: Vec<u64>,
+    pub maps: Vec<IntervalMap>,
 }
 
-pub fn get_min_dest_mapping(mappings: &Vec<Mapping>) -> &Mapping {
-    get_mapping_by_dest(mappings, min_value(mappings)).unwrap()
+impl Almanac {
+    /// The location of a seed, going through all sections one by one
+    pub fn location(&self, seed: u64) -> u64 {
+        resolve_seed(&self.maps, seed)
+    }
+
+    /// All sections composed into a single seed-to-location map
+    pub fn seed_to_location(&self) -> IntervalMap {
+        compose_all(&self.maps)
+    }
+
+    /// The lowest location of all seeds (part 1)
+    pub fn lowest_location(&self) -> Option<u64> {
+        let seed_to_location = self.seed_to_location();
+        self.seeds
+            .iter()
+            .map(|seed| seed_to_location.get(*seed))
+            .min()
+    }
+
+    /// The seeds read as pairs of start and length. An unpaired last number is ignored.
+    pub fn seed_ranges(&self) -> Vec<Range<u64>> {
+        self.seeds
+            .chunks_exact(2)
+            .map(|pair| pair[0]..pair[0].saturating_add(pair[1]))
+            .collect()
+    }
+
+    /// The lowest location of all seeds in the seed ranges (part 2)
+    pub fn lowest_location_of_ranges(&self) -> Option<u64> {
+        self.seed_to_location()
+            .map_ranges(&self.seed_ranges())
+            .iter()
+            .map(|range| range.start)
+            .min()
+    }
 }
 
-pub fn resolve_key(mappings: &Vec<Mapping>, key: u64) -> u64 {
-    for map in mappings {
-        if let Some(result) = map.map_source_to_destination(key) {
-            return result;
+/// Parses the seeds line and all sections. Each section starts with a "<name> map:" header.
+pub fn parse_almanac(input: &str) -> Result<Almanac, AlmanacError> {
+    let mut seeds: Option<Vec<u64>> = None;
+    let mut maps: Vec<IntervalMap> = vec![];
+    // the header line of the current section and its mappings
+    let mut section: Option<(usize, &str)> = None;
+    let mut mappings: Vec<Mapping> = vec![];
+
+    let lines: Vec<&str> = input.lines().collect();
+    for (idx, line) in lines.iter().enumerate() {
+        let error = |error| AlmanacError {
+            line_number: idx + 1,
+            line: line.to_string(),
+            error,
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if seeds.is_none() {
+            let numbers = line
+                .strip_prefix("seeds:")
+                .and_then(|numbers| {
+                    numbers
+                        .split_whitespace()
+                        .map(|number| number.parse().ok())
+                        .collect::<Option<Vec<u64>>>()
+                })
+                .ok_or_else(|| error(ParseAlmanacError::InvalidSeeds(line.to_string())))?;
+            seeds = Some(numbers);
+            continue;
+        }
+
+        if line.ends_with("map:") {
+            if let Some(header) = section.replace((idx, line)) {
+                maps.push(finish_section(header, std::mem::take(&mut mappings))?);
+            }
+            continue;
+        }
+
+        if section.is_none() {
+            return Err(error(ParseAlmanacError::InvalidMapping(line.to_string())));
         }
+        mappings.push(line.parse().map_err(error)?);
+    }
+    if let Some(header) = section {
+        maps.push(finish_section(header, mappings)?);
     }
 
-    key
+    Ok(Almanac {
+        seeds: seeds.unwrap_or_default(),
+        maps,
+    })
 }
 
-pub fn resolve_seed(mappings: &Vec<Vec<Mapping>>, seed: u64) -> u64 {
+fn finish_section(
+    (idx, header): (usize, &str),
+    mappings: Vec<Mapping>,
+) -> Result<IntervalMap, AlmanacError> {
+    IntervalMap::new(mappings).map_err(|error| AlmanacError {
+        line_number: idx + 1,
+        line: header.to_string(),
+        error,
+    })
+}
+
+pub fn resolve_seed(maps: &[IntervalMap], seed: u64) -> u64 {
     let mut key = seed;
 
-    for map in mappings {
-        key = resolve_key(&map, key);
+    for map in maps {
+        key = map.get(key);
     }
 
     key
 }
 
-pub fn solution2(input: &str) -> Option<usize> {
-    if let Some((seeds, maps)) = input.split_once("\n\n") {
-        let ids = seeds
-            .split_ascii_whitespace()
-            .filter_map(|id| id.parse::<usize>().ok())
-            .collect::<Vec<_>>();
-
-        let maps = maps
-            .split("\n\n")
-            .map(|m| {
-                m.lines()
-                    .skip(1)
-                    .map(|l| {
-                        l.split_ascii_whitespace()
-                            .filter_map(|num| num.parse::<usize>().ok())
-                            .collect::<Vec<usize>>()
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-
-        let mut arr = ids
-            .chunks_exact(2)
-            .map(|ele| ele[0]..(ele[0] + ele[1]))
-            .collect::<Vec<_>>();
-
-        for mut map in maps {
-            map.sort_unstable_by(|a, b| a[1].cmp(&b[1]));
-
-            let mut idx = 0;
-            loop {
-                if let None = arr.get_mut(idx) {
-                    break;
-                }
-
-                let current_range = arr[idx].clone();
-
-                for m in map.iter() {
-                    let destination = m[0];
-                    let source = m[1];
-                    let length = m[2];
-                    let range = source..(source + length);
-
-                    let current_start = current_range.start;
-                    let current_end = current_range.end - 1;
-
-                    let start_distance = current_start.saturating_sub(source);
-                    let end_distance = current_end.saturating_sub(source);
-
-                    if range.contains(&current_start) && range.contains(&current_end) {
-                        arr[idx] = (destination + start_distance)..(destination + end_distance);
-
-                        // println!("Range Contained -> {:?}", current_range);
-                        // println!("Output Range -> {:?}", arr[idx]);
-
-                        break;
-                    } else if range.contains(&current_start) && !range.contains(&current_end) {
-                        arr[idx] = (destination + start_distance)..(destination + length);
-                        let next_range = (source + length)..current_end + 1;
-
-                        // println!(
-                        //     "Start Included, End Excluded -> Split Range: {:?} -> {:?}",
-                        //     current_start..source + length,
-                        //     next_range
-                        // );
-                        // println!("Output Range -> {:?}", arr[idx]);
-
-                        arr.insert(idx + 1, next_range);
-                        // solved.insert(idx + 1, false);
-                        break;
-                    } else if !range.contains(&current_start) && range.contains(&current_end) {
-                        arr[idx] = (destination)..(destination + end_distance);
-                        let next_range = (current_start)..(source);
-
-                        // println!(
-                        //     "Start Excluded, End Included -> Split Range: {:?} -> {:?}",
-                        //     source..source + end_distance,
-                        //     next_range
-                        // );
-                        // println!("Output Range -> {:?}", arr[idx]);
-
-                        arr.insert(idx + 1, next_range);
-                        // solved.insert(idx + 1, false);
-                        break;
-                    }
-                    ()
-                }
-                idx += 1;
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4
+";
+
+    #[test]
+    fn test_almanac() {
+        let almanac = parse_almanac(EXAMPLE).unwrap();
+        assert_eq!(almanac.maps.len(), 7);
+        assert_eq!(almanac.maps[6].mappings().len(), 2);
+
+        let locations: Vec<u64> = almanac
+            .seeds
+            .iter()
+            .map(|seed| almanac.location(*seed))
+            .collect();
+        assert_eq!(locations, vec![82, 43, 86, 35]);
+        assert_eq!(almanac.lowest_location(), Some(35));
+
+        let seed_to_location = almanac.seed_to_location();
+        for seed in 0..120 {
+            assert_eq!(seed_to_location.get(seed), almanac.location(seed));
         }
+        assert_eq!(almanac.lowest_location_of_ranges(), Some(46));
+    }
 
-        let result = arr.iter().map(|r| r.start).min().unwrap();
-        Some(result)
-    } else {
-        None
+    #[test]
+    fn test_parse_errors() {
+        let err = parse_almanac("seeds: 1 2\n\na map:\n1 2 3\n1 2\n").unwrap_err();
+        assert_eq!(err.line_number, 5);
+        assert_eq!(
+            err.error,
+            ParseAlmanacError::InvalidMapping("1 2".to_string())
+        );
+
+        let err = parse_almanac("seeds: x\n").unwrap_err();
+        assert_eq!(
+            err.error,
+            ParseAlmanacError::InvalidSeeds("seeds: x".to_string())
+        );
+
+        let err = parse_almanac("seeds: 1\n\na map:\n1 2 3\n\nb map:\n0 0 5\n9 4 2\n").unwrap_err();
+        assert_eq!(err.line_number, 6);
+        assert_eq!(
+            err.to_string(),
+            "line 6: mappings '0 0 5' and '9 4 2' overlap in 'b map:'"
+        );
+
+        assert!("0 18446744073709551615 1".parse::<Mapping>().is_err());
     }
 }